@@ -1,3 +1,4 @@
+use crate::HttpLimits;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -24,6 +25,15 @@ struct ServerConfig {
 #[derive(Debug, Serialize, Deserialize)]
 pub struct HyperionConfig {
     servers: Vec<ServerConfig>,
+    /// Size limits of requests, see [`HttpServerBuilder::limits`](crate::HttpServerBuilder::limits)
+    #[serde(default)]
+    limits: HttpLimits,
+}
+
+impl Default for HyperionConfig {
+    fn default() -> Self {
+        HyperionConfig::new()
+    }
 }
 
 impl HyperionConfig {
@@ -46,6 +56,11 @@ impl HyperionConfig {
                     },
                 },
             ],
+            limits: HttpLimits::default(),
         }
     }
+
+    pub fn limits(&self) -> HttpLimits {
+        self.limits
+    }
 }
@@ -1,18 +1,291 @@
-#[derive(Debug, Clone)]
+use crate::{
+    http_body_reader::{from_io_error, BodyReader},
+    HttpError,
+};
+use futures::{
+    stream::{self, BoxStream},
+    Stream, StreamExt,
+};
+use std::{
+    fmt::Debug,
+    io,
+    sync::{Arc, Mutex},
+};
+
+/// A stream of byte chunks making up a streaming body
+pub type ByteStream = BoxStream<'static, io::Result<Vec<u8>>>;
+
+#[derive(Clone)]
+enum BodyKind {
+    Buffered(Vec<u8>),
+    /// Shared so the body stays `Clone`, while the stream itself can only be taken once
+    Stream(Arc<Mutex<StreamState>>),
+}
+
+enum StreamState {
+    Pending(ByteStream),
+    /// Collected by [`HttpBody::to_bytes`]
+    Buffered(Vec<u8>),
+    Taken,
+}
+
+#[derive(Clone)]
 pub struct HttpBody {
-    bytes: Vec<u8>,
+    kind: BodyKind,
 }
 
 impl HttpBody {
     pub fn new(bytes: Vec<u8>) -> HttpBody {
-        HttpBody { bytes }
+        HttpBody {
+            kind: BodyKind::Buffered(bytes),
+        }
+    }
+
+    /// A body whose chunks are written as `stream` produces them, for output too large or
+    /// too slow to buffer.
+    ///
+    /// Its length is unknown, so it is sent with `Transfer-Encoding: chunked` to HTTP/1.1
+    /// clients and by closing the connection once done to HTTP/1.0 clients. An error from
+    /// the stream aborts the response by closing the connection.
+    pub fn stream<S>(stream: S) -> HttpBody
+    where
+        S: Stream<Item = io::Result<Vec<u8>>> + Send + 'static,
+    {
+        HttpBody {
+            kind: BodyKind::Stream(Arc::new(Mutex::new(StreamState::Pending(stream.boxed())))),
+        }
     }
 
-    pub fn as_bytes(&self) -> Vec<u8> {
-        self.bytes.clone()
+    /// The bytes of a buffered body, or `None` for a streaming body not collected by
+    /// [`HttpBody::to_bytes`], whose bytes are only available by reading its stream. Use
+    /// [`HttpRequest::bytes`](crate::HttpRequest::bytes) for the body of a request.
+    pub fn as_bytes(&self) -> Option<Vec<u8>> {
+        match &self.kind {
+            BodyKind::Buffered(bytes) => Some(bytes.clone()),
+            BodyKind::Stream(state) => match &*lock(state) {
+                StreamState::Buffered(bytes) => Some(bytes.clone()),
+                StreamState::Pending(_) | StreamState::Taken => None,
+            },
+        }
+    }
+
+    /// The length of a buffered body, or `None` for a streaming body not collected by
+    /// [`HttpBody::to_bytes`]
+    pub fn len(&self) -> Option<usize> {
+        match &self.kind {
+            BodyKind::Buffered(bytes) => Some(bytes.len()),
+            BodyKind::Stream(state) => match &*lock(state) {
+                StreamState::Buffered(bytes) => Some(bytes.len()),
+                StreamState::Pending(_) | StreamState::Taken => None,
+            },
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == Some(0)
+    }
+
+    pub fn is_stream(&self) -> bool {
+        matches!(self.kind, BodyKind::Stream(_))
+    }
+
+    /// Takes the stream of a streaming body, which returns `None` for a buffered body and
+    /// once the stream has been taken
+    pub fn take_stream(&self) -> Option<ByteStream> {
+        let BodyKind::Stream(state) = &self.kind else {
+            return None;
+        };
+
+        match std::mem::replace(&mut *lock(state), StreamState::Taken) {
+            StreamState::Pending(stream) => Some(stream),
+            StreamState::Buffered(bytes) => Some(stream::once(async { Ok(bytes) }).boxed()),
+            StreamState::Taken => None,
+        }
+    }
+
+    /// Reads the body as an [`AsyncRead`](tokio::io::AsyncRead), taking the stream of a
+    /// streaming body. Nothing can be read once the stream has been taken.
+    pub fn reader(&self) -> BodyReader {
+        match &self.kind {
+            BodyKind::Buffered(bytes) => BodyReader::new(bytes.clone(), None),
+            BodyKind::Stream(_) => BodyReader::new(vec![], self.take_stream()),
+        }
+    }
+
+    /// Collects the body into memory, failing with [`HttpError::PayloadTooLarge`] once it
+    /// is longer than `max_size`. The bytes of a streaming body are kept, so the body can
+    /// be collected again.
+    pub async fn to_bytes(&self, max_size: usize) -> Result<Vec<u8>, HttpError> {
+        let state = match &self.kind {
+            BodyKind::Buffered(bytes) => return limit(bytes.clone(), max_size),
+            BodyKind::Stream(state) => state,
+        };
+
+        // Replaced in its own statement, so the lock is released before an arm takes it again
+        let previous = std::mem::replace(&mut *lock(state), StreamState::Taken);
+
+        let mut stream = match previous {
+            StreamState::Pending(stream) => stream,
+            StreamState::Buffered(bytes) => {
+                *lock(state) = StreamState::Buffered(bytes.clone());
+                return limit(bytes, max_size);
+            }
+            StreamState::Taken => {
+                return Err(HttpError::InternalServerError {
+                    message: "The body stream was already taken".to_string(),
+                })
+            }
+        };
+
+        let mut bytes = vec![];
+
+        while let Some(chunk) = stream.next().await {
+            bytes.extend(chunk.map_err(from_io_error)?);
+
+            // Checked as the chunks arrive, so an endless body is not read into memory
+            if bytes.len() > max_size {
+                return Err(too_large(max_size));
+            }
+        }
+
+        *lock(state) = StreamState::Buffered(bytes.clone());
+        Ok(bytes)
+    }
+}
+
+fn limit(bytes: Vec<u8>, max_size: usize) -> Result<Vec<u8>, HttpError> {
+    match bytes.len() > max_size {
+        true => Err(too_large(max_size)),
+        false => Ok(bytes),
+    }
+}
+
+/// The error for a body longer than `max_size` bytes
+pub(crate) fn too_large(max_size: usize) -> HttpError {
+    HttpError::PayloadTooLarge {
+        message: format!("Body larger than {max_size} bytes"),
     }
+}
+
+fn lock(state: &Mutex<StreamState>) -> std::sync::MutexGuard<'_, StreamState> {
+    state
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+impl Debug for HttpBody {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.kind {
+            BodyKind::Buffered(bytes) => f.debug_struct("HttpBody").field("bytes", bytes).finish(),
+            BodyKind::Stream(_) => f.debug_struct("HttpBody").finish_non_exhaustive(),
+        }
+    }
+}
+
+impl From<Vec<u8>> for HttpBody {
+    fn from(bytes: Vec<u8>) -> Self {
+        HttpBody::new(bytes)
+    }
+}
+
+impl From<String> for HttpBody {
+    fn from(string: String) -> Self {
+        HttpBody::new(string.into_bytes())
+    }
+}
+
+impl From<&str> for HttpBody {
+    fn from(string: &str) -> Self {
+        HttpBody::new(string.as_bytes().to_vec())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::AsyncReadExt;
+
+    fn chunks(chunks: &[&'static str]) -> HttpBody {
+        let chunks: Vec<_> = chunks
+            .iter()
+            .map(|chunk| Ok(chunk.as_bytes().to_vec()))
+            .collect();
+        HttpBody::stream(stream::iter(chunks))
+    }
+
+    #[tokio::test]
+    async fn collects_streaming_bodies_once() {
+        let body = chunks(&["hello", " ", "world"]);
+        assert_eq!(body.as_bytes(), None);
+        assert_eq!(body.len(), None);
+
+        assert_eq!(body.to_bytes(1024).await.unwrap(), b"hello world");
+        assert_eq!(body.to_bytes(1024).await.unwrap(), b"hello world");
+        assert_eq!(body.as_bytes().unwrap(), b"hello world");
+        assert_eq!(body.len(), Some(11));
+    }
+
+    #[tokio::test]
+    async fn limits_the_size_of_collected_bodies() {
+        let body = chunks(&["hello", " ", "world"]);
+        assert!(matches!(
+            body.to_bytes(10).await,
+            Err(HttpError::PayloadTooLarge { .. })
+        ));
+
+        assert_eq!(HttpBody::from("hello").to_bytes(5).await.unwrap(), b"hello");
+        assert!(matches!(
+            HttpBody::from("hello").to_bytes(4).await,
+            Err(HttpError::PayloadTooLarge { .. })
+        ));
+
+        let body = chunks(&["hello"]);
+        body.to_bytes(5).await.unwrap();
+        assert!(body.to_bytes(4).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn fails_once_the_stream_is_taken() {
+        let body = chunks(&["hello"]);
+        assert!(body.take_stream().is_some());
+        assert!(body.take_stream().is_none());
+        assert_eq!(body.as_bytes(), None);
+        assert!(matches!(
+            body.to_bytes(1024).await,
+            Err(HttpError::InternalServerError { .. })
+        ));
+        assert!(HttpBody::from("hello").take_stream().is_none());
+    }
+
+    #[tokio::test]
+    async fn reports_stream_errors() {
+        let body = HttpBody::stream(stream::iter(vec![
+            Ok(b"hello".to_vec()),
+            Err(io::Error::from(io::ErrorKind::ConnectionReset)),
+        ]));
+
+        assert!(matches!(
+            body.to_bytes(1024).await,
+            Err(HttpError::IoError(error)) if error.kind() == io::ErrorKind::ConnectionReset
+        ));
+    }
+
+    #[tokio::test]
+    async fn reads_bodies_through_a_reader() {
+        let mut read = String::new();
+        chunks(&["hello", "", " world"])
+            .reader()
+            .read_to_string(&mut read)
+            .await
+            .unwrap();
+        assert_eq!(read, "hello world");
+
+        let body = HttpBody::from("buffered");
+        let mut read = String::new();
+        body.reader().read_to_string(&mut read).await.unwrap();
+        assert_eq!(read, "buffered");
 
-    pub fn len(&self) -> usize {
-        self.bytes.len()
+        // A buffered body can be read again
+        assert_eq!(body.as_bytes().unwrap(), b"buffered");
     }
 }
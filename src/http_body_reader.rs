@@ -0,0 +1,275 @@
+use crate::{
+    http_body::{too_large, ByteStream},
+    http_chunked::{read_chunk_end, read_chunk_size, read_trailers},
+    HttpError, HttpHeaderMap, HttpLimits,
+};
+use futures::{stream, Stream, StreamExt};
+use std::{
+    io,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+};
+use tokio::{
+    io::{AsyncBufRead, AsyncBufReadExt, AsyncRead, BufReader, ReadBuf},
+    net::tcp::OwnedReadHalf,
+};
+
+/// The read half of a connection, shared between the server and the body of the request
+/// being handled
+pub(crate) type SharedReader = Arc<tokio::sync::Mutex<BufReader<OwnedReadHalf>>>;
+
+/// Where the body of a request ends
+#[derive(Debug, Clone, Copy)]
+enum Framing {
+    /// `Content-Length`, with the number of bytes left
+    Length(usize),
+    /// `Transfer-Encoding: chunked`, with the number of bytes left in the current chunk,
+    /// whether a chunk has been started yet and the size of the chunks so far
+    Chunked {
+        remaining: usize,
+        started: bool,
+        size: usize,
+    },
+    Done,
+    /// The body is malformed or the connection broke, so where it ends is unknown
+    Failed,
+}
+
+/// The body of a request, read from the connection only as the handler asks for it
+pub(crate) struct IncomingBody {
+    reader: SharedReader,
+    framing: Framing,
+    trailers: Arc<Mutex<HttpHeaderMap>>,
+    limits: HttpLimits,
+}
+
+impl IncomingBody {
+    /// A body with a `Content-Length` of `length`, already checked against `limits`
+    pub(crate) fn with_length(
+        reader: SharedReader,
+        length: usize,
+        limits: &HttpLimits,
+    ) -> IncomingBody {
+        IncomingBody {
+            reader,
+            framing: Framing::Length(length),
+            trailers: Arc::default(),
+            limits: *limits,
+        }
+    }
+
+    /// A `Transfer-Encoding: chunked` body, whose trailers are stored in `trailers` once the
+    /// last chunk has been read. Its size is only known as it is read, so `limits` are
+    /// checked chunk by chunk.
+    pub(crate) fn chunked(
+        reader: SharedReader,
+        trailers: Arc<Mutex<HttpHeaderMap>>,
+        limits: &HttpLimits,
+    ) -> IncomingBody {
+        IncomingBody {
+            reader,
+            framing: Framing::Chunked {
+                remaining: 0,
+                started: false,
+                size: 0,
+            },
+            trailers,
+            limits: *limits,
+        }
+    }
+
+    /// Whether the body has been read up to its end, so the next request on the connection
+    /// can be read
+    pub(crate) fn is_complete(&self) -> bool {
+        matches!(self.framing, Framing::Done)
+    }
+
+    /// The number of bytes left in a body with a `Content-Length`
+    pub(crate) fn remaining_length(&self) -> Option<usize> {
+        match self.framing {
+            Framing::Length(remaining) => Some(remaining),
+            _ => None,
+        }
+    }
+
+    /// Reads the next bytes of the body, returning `None` once it ends. A failed read ends
+    /// the body as well.
+    pub(crate) async fn next_chunk(&mut self) -> Result<Option<Vec<u8>>, HttpError> {
+        let reader = self.reader.clone();
+        let mut reader = reader.lock().await;
+
+        let chunk = self.read_chunk(&mut *reader).await;
+
+        if chunk.is_err() {
+            self.framing = Framing::Failed;
+        }
+
+        chunk
+    }
+
+    async fn read_chunk<R: AsyncBufRead + Unpin>(
+        &mut self,
+        reader: &mut R,
+    ) -> Result<Option<Vec<u8>>, HttpError> {
+        loop {
+            match self.framing {
+                Framing::Length(0) => self.framing = Framing::Done,
+                Framing::Length(remaining) => {
+                    let chunk = read_available(reader, remaining).await?;
+                    self.framing = Framing::Length(remaining - chunk.len());
+                    return Ok(Some(chunk));
+                }
+                Framing::Chunked {
+                    remaining: 0,
+                    started,
+                    size,
+                } => {
+                    if started {
+                        read_chunk_end(reader).await?;
+                    }
+
+                    let chunk_size = read_chunk_size(reader).await?;
+
+                    if chunk_size > self.limits.max_body_size - size {
+                        return Err(too_large(self.limits.max_body_size));
+                    }
+
+                    if chunk_size == 0 {
+                        let trailers = read_trailers(reader, self.limits.max_header_size).await?;
+                        *self
+                            .trailers
+                            .lock()
+                            .unwrap_or_else(|poisoned| poisoned.into_inner()) = trailers;
+                        self.framing = Framing::Done;
+                    } else {
+                        self.framing = Framing::Chunked {
+                            remaining: chunk_size,
+                            started: true,
+                            size: size + chunk_size,
+                        };
+                    }
+                }
+                Framing::Chunked {
+                    remaining, size, ..
+                } => {
+                    let chunk = read_available(reader, remaining).await?;
+                    self.framing = Framing::Chunked {
+                        remaining: remaining - chunk.len(),
+                        started: true,
+                        size,
+                    };
+                    return Ok(Some(chunk));
+                }
+                Framing::Done | Framing::Failed => return Ok(None),
+            }
+        }
+    }
+
+    /// The body as a stream for [`HttpBody::stream`](crate::HttpBody::stream), sharing its
+    /// progress with `body`
+    pub(crate) fn into_stream(body: Arc<tokio::sync::Mutex<IncomingBody>>) -> ByteStream {
+        stream::unfold(body, |body| async move {
+            let chunk = body.lock().await.next_chunk().await;
+
+            match chunk {
+                Ok(Some(chunk)) => Some((Ok(chunk), body)),
+                Ok(None) => None,
+                Err(error) => Some((Err(into_io_error(error)), body)),
+            }
+        })
+        .boxed()
+    }
+}
+
+/// Reads the bytes already buffered, waiting for more only if there are none, up to `limit`
+async fn read_available<R: AsyncBufRead + Unpin>(
+    reader: &mut R,
+    limit: usize,
+) -> Result<Vec<u8>, HttpError> {
+    let buffer = reader.fill_buf().await?;
+
+    if buffer.is_empty() {
+        return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+    }
+
+    let chunk = buffer[..buffer.len().min(limit)].to_vec();
+    reader.consume(chunk.len());
+
+    Ok(chunk)
+}
+
+/// Wraps `error` so it can travel through a [`ByteStream`], see [`from_io_error`]
+fn into_io_error(error: HttpError) -> io::Error {
+    match error {
+        HttpError::IoError(error) => error,
+        error => io::Error::new(io::ErrorKind::InvalidData, error),
+    }
+}
+
+/// Recovers the [`HttpError`] wrapped by [`into_io_error`], e.g. a 400 for a malformed
+/// chunk, or wraps any other error
+pub(crate) fn from_io_error(error: io::Error) -> HttpError {
+    if !error.get_ref().is_some_and(|inner| inner.is::<HttpError>()) {
+        return HttpError::IoError(error);
+    }
+
+    match error
+        .into_inner()
+        .map(|inner| inner.downcast::<HttpError>())
+    {
+        Some(Ok(error)) => *error,
+        _ => unreachable!("the inner error was checked to be an HttpError"),
+    }
+}
+
+/// An [`AsyncRead`] over the bytes of an [`HttpBody`](crate::HttpBody), see
+/// [`HttpBody::reader`](crate::HttpBody::reader)
+pub struct BodyReader {
+    stream: Option<ByteStream>,
+    /// The chunk being read and how much of it has been read
+    chunk: Vec<u8>,
+    position: usize,
+}
+
+impl BodyReader {
+    pub(crate) fn new(chunk: Vec<u8>, stream: Option<ByteStream>) -> BodyReader {
+        BodyReader {
+            stream,
+            chunk,
+            position: 0,
+        }
+    }
+}
+
+impl AsyncRead for BodyReader {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let reader = &mut *self;
+
+        while reader.position == reader.chunk.len() {
+            let Some(stream) = reader.stream.as_mut() else {
+                return Poll::Ready(Ok(()));
+            };
+
+            match Pin::new(stream).poll_next(cx) {
+                Poll::Ready(Some(Ok(chunk))) => {
+                    reader.chunk = chunk;
+                    reader.position = 0;
+                }
+                Poll::Ready(Some(Err(error))) => return Poll::Ready(Err(error)),
+                Poll::Ready(None) => reader.stream = None,
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+
+        let length = buf.remaining().min(reader.chunk.len() - reader.position);
+        buf.put_slice(&reader.chunk[reader.position..reader.position + length]);
+        reader.position += length;
+
+        Poll::Ready(Ok(()))
+    }
+}
@@ -0,0 +1,240 @@
+use crate::{HttpError, HttpHeader, HttpHeaderMap};
+use tokio::io::{AsyncBufRead, AsyncBufReadExt};
+
+/// Maximum length of a chunk size line or trailer line
+const MAX_LINE_LENGTH: usize = 0x2000;
+
+/// The chunk ending a chunked body, without trailers
+pub(crate) const LAST_CHUNK: &[u8] = b"0\r\n\r\n";
+
+/// Frames `chunk` as a chunk of a chunked body. An empty chunk is left empty, as it would
+/// otherwise end the body.
+pub(crate) fn encode_chunk(chunk: &[u8]) -> Vec<u8> {
+    if chunk.is_empty() {
+        return vec![];
+    }
+
+    let mut encoded = format!("{:x}\r\n", chunk.len()).into_bytes();
+    encoded.extend_from_slice(chunk);
+    encoded.extend_from_slice(b"\r\n");
+    encoded
+}
+
+/// Reads a line terminated by `\n`, returning it without the terminator and failing with
+/// `error` if it is longer than `limit` bytes
+pub(crate) async fn read_line<R: AsyncBufRead + Unpin>(
+    reader: &mut R,
+    limit: usize,
+    error: impl Fn() -> HttpError,
+) -> Result<Vec<u8>, HttpError> {
+    let mut line = vec![];
+
+    loop {
+        let buffer = reader.fill_buf().await?;
+
+        if buffer.is_empty() {
+            return Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof).into());
+        }
+
+        let (length, found) = match buffer.iter().position(|byte| *byte == b'\n') {
+            Some(index) => (index + 1, true),
+            None => (buffer.len(), false),
+        };
+
+        if line.len() + length > limit {
+            return Err(error());
+        }
+
+        line.extend_from_slice(&buffer[..length]);
+        reader.consume(length);
+
+        if found {
+            line.pop();
+            return Ok(line);
+        }
+    }
+}
+
+/// Reads a line that must end with `\r\n`, failing with [`HttpError::BadRequest`] on a bare
+/// `\n` or a line longer than `limit` bytes
+async fn read_crlf_line<R: AsyncBufRead + Unpin>(
+    reader: &mut R,
+    limit: usize,
+) -> Result<Vec<u8>, HttpError> {
+    let mut line = read_line(reader, limit, || bad_request("Chunked body line too long")).await?;
+
+    if line.pop() != Some(b'\r') {
+        return Err(bad_request("Chunked body line without CRLF"));
+    }
+
+    Ok(line)
+}
+
+fn bad_request(message: &str) -> HttpError {
+    HttpError::BadRequest {
+        message: message.to_string(),
+    }
+}
+
+/// Reads the line starting a chunk of a `Transfer-Encoding: chunked` body, as defined by
+/// RFC 9112, section 7.1, returning the size of the chunk. Chunk extensions are ignored.
+pub(crate) async fn read_chunk_size<R: AsyncBufRead + Unpin>(
+    reader: &mut R,
+) -> Result<usize, HttpError> {
+    let line = read_crlf_line(reader, MAX_LINE_LENGTH).await?;
+    let line = std::str::from_utf8(&line).map_err(|_| bad_request("Invalid chunk size"))?;
+
+    let size = line
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim_end_matches([' ', '\t']);
+
+    if size.is_empty() || !size.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+        return Err(bad_request("Invalid chunk size"));
+    }
+
+    usize::from_str_radix(size, 16).map_err(|_| HttpError::PayloadTooLarge {
+        message: "Chunk size too large".to_string(),
+    })
+}
+
+/// Reads the line break following the data of a chunk
+pub(crate) async fn read_chunk_end<R: AsyncBufRead + Unpin>(
+    reader: &mut R,
+) -> Result<(), HttpError> {
+    match read_line(reader, 2, || bad_request("Chunk longer than its size")).await? == b"\r" {
+        true => Ok(()),
+        false => Err(bad_request("Chunk longer than its size")),
+    }
+}
+
+/// Reads the trailer fields following the last chunk, up to the empty line ending the body,
+/// failing with [`HttpError::RequestHeaderFieldsTooLarge`] once they are larger than
+/// `max_size`
+pub(crate) async fn read_trailers<R: AsyncBufRead + Unpin>(
+    reader: &mut R,
+    max_size: usize,
+) -> Result<HttpHeaderMap, HttpError> {
+    let mut trailers = HttpHeaderMap::new();
+    let mut size = 0;
+
+    loop {
+        let line = read_crlf_line(reader, MAX_LINE_LENGTH).await?;
+
+        if line.is_empty() {
+            return Ok(trailers);
+        }
+
+        size += line.len() + 2;
+
+        if size > max_size {
+            return Err(HttpError::RequestHeaderFieldsTooLarge {
+                message: format!("Trailers larger than {max_size} bytes"),
+            });
+        }
+
+        let line = std::str::from_utf8(&line)
+            .map_err(|_| bad_request("Invalid UTF-8 Sequence in trailers"))?;
+
+        trailers.append(HttpHeader::parse(line)?);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn chunk_size(line: &[u8]) -> Result<usize, HttpError> {
+        read_chunk_size(&mut &line[..]).await
+    }
+
+    #[test]
+    fn encodes_chunks() {
+        assert_eq!(encode_chunk(b"hello world"), b"b\r\nhello world\r\n");
+        assert!(encode_chunk(b"").is_empty());
+    }
+
+    #[tokio::test]
+    async fn reads_chunk_sizes() {
+        assert_eq!(chunk_size(b"1a\r\n").await.unwrap(), 26);
+        assert_eq!(chunk_size(b"FF\r\n").await.unwrap(), 255);
+        assert_eq!(chunk_size(b"0\r\n").await.unwrap(), 0);
+        assert_eq!(chunk_size(b"5;name=value \r\n").await.unwrap(), 5);
+        assert_eq!(chunk_size(b"5 \t;ext\r\n").await.unwrap(), 5);
+    }
+
+    #[tokio::test]
+    async fn rejects_invalid_chunk_sizes() {
+        for line in [
+            &b"\r\n"[..],
+            b"-1\r\n",
+            b"+1\r\n",
+            b"0x10\r\n",
+            b"1 0\r\n",
+            b" 10\r\n",
+            b"g\r\n",
+            b"10\n",
+            b"10",
+        ] {
+            assert!(
+                chunk_size(line).await.is_err(),
+                "{}",
+                String::from_utf8_lossy(line).escape_debug()
+            );
+        }
+
+        assert!(matches!(
+            chunk_size(b"10000000000000000\r\n").await,
+            Err(HttpError::PayloadTooLarge { .. })
+        ));
+        assert!(matches!(
+            chunk_size(format!("1;{}\r\n", "x".repeat(MAX_LINE_LENGTH)).as_bytes()).await,
+            Err(HttpError::BadRequest { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn requires_a_line_break_after_each_chunk() {
+        assert!(read_chunk_end(&mut &b"\r\n"[..]).await.is_ok());
+        assert!(read_chunk_end(&mut &b"x\r\n"[..]).await.is_err());
+        assert!(read_chunk_end(&mut &b"\n"[..]).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn reads_trailers() {
+        let trailers = read_trailers(&mut &b"Checksum: abc\r\nX-Done: yes\r\n\r\n"[..], 1024)
+            .await
+            .unwrap();
+
+        assert_eq!(trailers.get("Checksum"), Some("abc"));
+        assert_eq!(trailers.get("X-Done"), Some("yes"));
+        assert!(read_trailers(&mut &b"\r\n"[..], 1024)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn rejects_invalid_trailers() {
+        assert!(matches!(
+            read_trailers(&mut &b"Checksum: abc\r\n\r\n"[..], 14).await,
+            Err(HttpError::RequestHeaderFieldsTooLarge { .. })
+        ));
+        assert!(read_trailers(&mut &b"Checksum: abc\r\n\r\n"[..], 15)
+            .await
+            .is_ok());
+
+        assert!(matches!(
+            read_trailers(&mut &b"No colon\r\n\r\n"[..], 1024).await,
+            Err(HttpError::BadRequest { .. })
+        ));
+        assert!(matches!(
+            read_trailers(&mut &b"Checksum: abc\n\r\n"[..], 1024).await,
+            Err(HttpError::BadRequest { .. })
+        ));
+        assert!(read_trailers(&mut &b"Checksum: abc\r\n"[..], 1024)
+            .await
+            .is_err());
+    }
+}
@@ -1,65 +1,156 @@
-use crate::HttpHeader;
-use chrono::{DateTime, Utc};
+use crate::{format_http_date, http_header::is_token, HttpError, HttpHeader};
+use chrono::{DateTime, Duration, TimeZone, Utc};
 use std::fmt::Display;
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SameSite {
+    Strict,
+    Lax,
+    None,
+}
+
+impl Display for SameSite {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let same_site = match self {
+            SameSite::Strict => "Strict",
+            SameSite::Lax => "Lax",
+            SameSite::None => "None",
+        };
+
+        write!(f, "{same_site}")
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct HttpCookie {
-    pub name: String,
+    name: String,
     value: String,
     secure: bool,
+    http_only: bool,
     domain: Option<String>,
     path: Option<String>,
     expires: Option<DateTime<Utc>>,
-    same_site: Option<String>,
+    max_age: Option<Duration>,
+    same_site: Option<SameSite>,
 }
 
 impl HttpCookie {
+    pub fn builder(name: &str, value: &str) -> HttpCookieBuilder {
+        HttpCookieBuilder {
+            cookie: HttpCookie::new(name, value),
+        }
+    }
+
+    /// The `Set-Cookie` response header setting this cookie
     pub fn to_header(&self) -> HttpHeader {
-        let attributes: Vec<_> = self
-            .get_attributes()
-            .iter()
-            .map(|attribute| format!("{}={}", attribute.0, attribute.1))
-            .collect();
+        let mut value = format!("{}={}", self.name, self.value);
 
-        HttpHeader {
-            name: "Cookie".to_string(),
-            value: format!("{}={}; {}", self.name, self.value, attributes.join("; ")),
+        for attribute in self.get_attributes() {
+            value.push_str("; ");
+            value.push_str(&attribute);
         }
+
+        // Names are tokens, and values, domains and paths are checked by the builder or come
+        // from a header that was already validated
+        HttpHeader::new_unchecked("Set-Cookie", value)
+    }
+
+    /// Parses every `name=value` pair of a `Cookie` request header.
+    ///
+    /// Pairs are separated by `;` with optional whitespace around them, and a value wrapped
+    /// in double quotes has the quotes removed (RFC 6265, section 5.4). Pairs without a `=`
+    /// or with an empty name are skipped.
+    pub fn from_header(header: &HttpHeader) -> Vec<HttpCookie> {
+        header
+            .value()
+            .split(';')
+            .filter_map(|pair| {
+                let (name, value) = pair.split_once('=')?;
+                let (name, value) = (name.trim(), value.trim());
+
+                if name.is_empty() {
+                    return None;
+                }
+
+                let value = value
+                    .strip_prefix('"')
+                    .and_then(|value| value.strip_suffix('"'))
+                    .unwrap_or(value);
+
+                Some(HttpCookie::new(name, value))
+            })
+            .collect()
     }
 
-    pub fn from_header(header: &HttpHeader) -> HttpCookie {
+    fn new(name: &str, value: &str) -> HttpCookie {
         HttpCookie {
-            name: "Cookie".to_string(),
-            value: "".to_string(),
+            name: name.to_string(),
+            value: value.to_string(),
             secure: false,
+            http_only: false,
             domain: None,
             path: None,
             expires: None,
+            max_age: None,
             same_site: None,
         }
     }
 
-    pub fn get_attributes(&self) -> Vec<(String, String)> {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+
+    pub(crate) fn with_value(&self, value: String) -> HttpCookie {
+        HttpCookie {
+            value,
+            ..self.clone()
+        }
+    }
+
+    /// A cookie with the same name, domain and path that makes the client delete this one
+    pub fn to_removal(&self) -> HttpCookie {
+        HttpCookie {
+            value: String::new(),
+            expires: Some(Utc.timestamp_opt(0, 0).unwrap()),
+            max_age: Some(Duration::zero()),
+            ..self.clone()
+        }
+    }
+
+    /// The attributes of the `Set-Cookie` header, e.g. `Path=/` or `HttpOnly`
+    pub fn get_attributes(&self) -> Vec<String> {
         let mut attributes = vec![];
 
-        if self.secure {
-            attributes.push(("Secure".to_string(), "".to_string()));
+        if let Some(expires) = &self.expires {
+            attributes.push(format!("Expires={}", format_http_date(*expires)));
         }
 
-        if let Some(same_site) = &self.same_site {
-            attributes.push(("Same-Site".to_string(), same_site.to_string()));
+        if let Some(max_age) = &self.max_age {
+            attributes.push(format!("Max-Age={}", max_age.num_seconds()));
         }
 
         if let Some(domain) = &self.domain {
-            attributes.push(("Domain".to_string(), domain.to_string()));
+            attributes.push(format!("Domain={domain}"));
         }
 
         if let Some(path) = &self.path {
-            attributes.push(("Path".to_string(), path.to_string()));
+            attributes.push(format!("Path={path}"));
         }
 
-        if let Some(expires) = &self.expires {
-            attributes.push(("Expires".to_string(), expires.format("%+").to_string()));
+        if self.secure {
+            attributes.push("Secure".to_string());
+        }
+
+        if self.http_only {
+            attributes.push("HttpOnly".to_string());
+        }
+
+        if let Some(same_site) = &self.same_site {
+            attributes.push(format!("SameSite={same_site}"));
         }
 
         attributes
@@ -71,3 +162,276 @@ impl Display for HttpCookie {
         write!(f, "{}", self.to_header())
     }
 }
+
+/// Builds an [`HttpCookie`] to be sent with [`HttpResponse::set_cookie`](crate::HttpResponse::set_cookie)
+pub struct HttpCookieBuilder {
+    cookie: HttpCookie,
+}
+
+impl HttpCookieBuilder {
+    pub fn secure(mut self, secure: bool) -> HttpCookieBuilder {
+        self.cookie.secure = secure;
+        self
+    }
+
+    pub fn http_only(mut self, http_only: bool) -> HttpCookieBuilder {
+        self.cookie.http_only = http_only;
+        self
+    }
+
+    pub fn domain(mut self, domain: &str) -> HttpCookieBuilder {
+        self.cookie.domain = Some(domain.to_string());
+        self
+    }
+
+    pub fn path(mut self, path: &str) -> HttpCookieBuilder {
+        self.cookie.path = Some(path.to_string());
+        self
+    }
+
+    pub fn expires(mut self, expires: DateTime<Utc>) -> HttpCookieBuilder {
+        self.cookie.expires = Some(expires);
+        self
+    }
+
+    pub fn max_age(mut self, max_age: Duration) -> HttpCookieBuilder {
+        self.cookie.max_age = Some(max_age);
+        self
+    }
+
+    pub fn same_site(mut self, same_site: SameSite) -> HttpCookieBuilder {
+        self.cookie.same_site = Some(same_site);
+        self
+    }
+
+    /// Validates the cookie against RFC 6265, failing with
+    /// [`HttpError::InternalServerError`] if the name is not a token, the value has characters
+    /// outside of cookie-octets, the domain or path contain control characters or `;`, or
+    /// `SameSite=None` is set without `Secure`, which browsers would reject
+    pub fn build(self) -> Result<HttpCookie, HttpError> {
+        let cookie = self.cookie;
+
+        if !is_token(&cookie.name) {
+            return Err(HttpError::InternalServerError {
+                message: format!("Invalid cookie name `{}`", cookie.name),
+            });
+        }
+
+        let value = cookie
+            .value
+            .strip_prefix('"')
+            .and_then(|value| value.strip_suffix('"'))
+            .unwrap_or(&cookie.value);
+
+        if !value.bytes().all(is_cookie_octet) {
+            return Err(HttpError::InternalServerError {
+                message: format!("Invalid value for cookie `{}`", cookie.name),
+            });
+        }
+
+        let is_valid_attribute = |value: &str| {
+            !value
+                .chars()
+                .any(|char| char.is_ascii_control() || char == ';')
+        };
+
+        if !cookie
+            .domain
+            .iter()
+            .chain(&cookie.path)
+            .all(|value| is_valid_attribute(value))
+        {
+            return Err(HttpError::InternalServerError {
+                message: format!("Invalid domain or path for cookie `{}`", cookie.name),
+            });
+        }
+
+        if cookie.same_site == Some(SameSite::None) && !cookie.secure {
+            return Err(HttpError::InternalServerError {
+                message: format!("Cookie `{}` has SameSite=None without Secure", cookie.name),
+            });
+        }
+
+        Ok(cookie)
+    }
+}
+
+/// `cookie-octet` from RFC 6265, section 4.1.1
+fn is_cookie_octet(byte: u8) -> bool {
+    matches!(byte, 0x21 | 0x23..=0x2B | 0x2D..=0x3A | 0x3C..=0x5B | 0x5D..=0x7E)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{HttpRequest, HttpResponse};
+
+    fn parse(value: &str) -> Vec<(String, String)> {
+        HttpCookie::from_header(&HttpHeader::new("Cookie", value).unwrap())
+            .into_iter()
+            .map(|cookie| (cookie.name, cookie.value))
+            .collect()
+    }
+
+    fn pairs(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn parses_cookie_headers() {
+        assert_eq!(
+            parse("a=1; b=2;c=3"),
+            pairs(&[("a", "1"), ("b", "2"), ("c", "3")])
+        );
+        assert_eq!(parse(" a = 1 ;  b=2 "), pairs(&[("a", "1"), ("b", "2")]));
+        assert_eq!(
+            parse("a=\"quoted\"; b=\"; c=\"\""),
+            pairs(&[("a", "quoted"), ("b", "\""), ("c", "")])
+        );
+        assert_eq!(parse("a=x=y"), pairs(&[("a", "x=y")]));
+    }
+
+    #[test]
+    fn skips_malformed_cookie_pairs() {
+        assert_eq!(
+            parse("flag; =value; a=1;; b=2"),
+            pairs(&[("a", "1"), ("b", "2")])
+        );
+        assert!(parse("").is_empty());
+    }
+
+    #[test]
+    fn keeps_duplicate_cookie_names() {
+        assert_eq!(parse("a=1; a=2"), pairs(&[("a", "1"), ("a", "2")]));
+    }
+
+    #[test]
+    fn reads_every_cookie_header_of_a_request() {
+        let request = HttpRequest::new(
+            b"GET / HTTP/1.1\r\nHost: localhost\r\nCookie: a=1; b=2\r\ncookie: a=3; c=4".to_vec(),
+        )
+        .unwrap();
+
+        let cookies = request
+            .get_cookies()
+            .into_iter()
+            .map(|cookie| (cookie.name, cookie.value))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            cookies,
+            pairs(&[("a", "1"), ("b", "2"), ("a", "3"), ("c", "4")])
+        );
+        assert_eq!(request.get_cookie("a").unwrap().value(), "1");
+        assert_eq!(request.get_cookie("c").unwrap().value(), "4");
+        assert!(request.get_cookie("d").is_none());
+    }
+
+    #[test]
+    fn serializes_cookie_attributes() {
+        let cookie = HttpCookie::builder("id", "abc")
+            .expires(Utc.timestamp_opt(1445412480, 0).unwrap())
+            .max_age(Duration::hours(1))
+            .domain("example.com")
+            .path("/app")
+            .secure(true)
+            .http_only(true)
+            .same_site(SameSite::Strict)
+            .build()
+            .unwrap();
+
+        let header = cookie.to_header();
+        assert_eq!(header.name(), "Set-Cookie");
+        assert_eq!(
+            header.value(),
+            "id=abc; Expires=Wed, 21 Oct 2015 07:28:00 GMT; Max-Age=3600; \
+             Domain=example.com; Path=/app; Secure; HttpOnly; SameSite=Strict"
+        );
+        assert_eq!(
+            HttpCookie::builder("id", "abc")
+                .build()
+                .unwrap()
+                .to_header()
+                .value(),
+            "id=abc"
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_cookies() {
+        let builders = [
+            HttpCookie::builder("", "abc"),
+            HttpCookie::builder("a b", "abc"),
+            HttpCookie::builder("a;b", "abc"),
+            HttpCookie::builder("id", "a b"),
+            HttpCookie::builder("id", "a;b"),
+            HttpCookie::builder("id", "a,b"),
+            HttpCookie::builder("id", "a\\b"),
+            HttpCookie::builder("id", "\"abc"),
+            HttpCookie::builder("id", "abc").domain("example.com; Secure"),
+            HttpCookie::builder("id", "abc").domain("example.com\r\nX: y"),
+            HttpCookie::builder("id", "abc").path("/; HttpOnly"),
+            HttpCookie::builder("id", "abc").path("/\n"),
+            HttpCookie::builder("id", "abc").same_site(SameSite::None),
+        ];
+
+        for builder in builders {
+            assert!(matches!(
+                builder.build(),
+                Err(HttpError::InternalServerError { .. })
+            ));
+        }
+
+        assert!(HttpCookie::builder("id", "\"abc\"").build().is_ok());
+        assert!(HttpCookie::builder("id", "").build().is_ok());
+        assert!(HttpCookie::builder("id", "abc")
+            .same_site(SameSite::None)
+            .secure(true)
+            .build()
+            .is_ok());
+    }
+
+    #[test]
+    fn sets_one_header_per_cookie() {
+        let mut response = HttpResponse::ok("");
+        response.set_cookie(&HttpCookie::builder("a", "1").build().unwrap());
+        response.set_cookie(&HttpCookie::builder("b", "2").path("/").build().unwrap());
+
+        assert_eq!(
+            response.headers().get_all("Set-Cookie").collect::<Vec<_>>(),
+            ["a=1", "b=2; Path=/"]
+        );
+        assert_eq!(
+            response
+                .to_string()
+                .lines()
+                .filter(|line| line.starts_with("Set-Cookie: "))
+                .count(),
+            2
+        );
+    }
+
+    #[test]
+    fn removes_cookies() {
+        let cookie = HttpCookie::builder("id", "abc")
+            .domain("example.com")
+            .path("/app")
+            .max_age(Duration::hours(1))
+            .secure(true)
+            .build()
+            .unwrap();
+
+        let mut response = HttpResponse::ok("");
+        response.remove_cookie(&cookie);
+
+        assert_eq!(
+            response.get_header("Set-Cookie"),
+            Some(
+                "id=; Expires=Thu, 01 Jan 1970 00:00:00 GMT; Max-Age=0; \
+                 Domain=example.com; Path=/app; Secure"
+            )
+        );
+    }
+}
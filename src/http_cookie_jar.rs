@@ -0,0 +1,336 @@
+use crate::HttpCookie;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chacha20poly1305::{
+    aead::{Aead, KeyInit, Payload},
+    Key, XChaCha20Poly1305, XNonce,
+};
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use sha2::Sha256;
+use std::fmt::Debug;
+
+type HmacSha256 = Hmac<Sha256>;
+
+const KEY_LENGTH: usize = 32;
+const NONCE_LENGTH: usize = 24;
+/// Length of a base64 encoded HMAC-SHA256 tag without padding
+const SIGNATURE_LENGTH: usize = 43;
+
+/// A secret used to sign and encrypt cookies
+#[derive(Clone)]
+pub struct CookieKey {
+    signing: [u8; KEY_LENGTH],
+    encryption: [u8; KEY_LENGTH],
+}
+
+impl CookieKey {
+    /// Derives separate signing and encryption keys from `secret`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `secret` is shorter than 32 bytes.
+    pub fn from_secret(secret: &[u8]) -> CookieKey {
+        if secret.len() < KEY_LENGTH {
+            panic!("Cookie secrets must be at least {KEY_LENGTH} bytes long");
+        }
+
+        let derive = |purpose: &[u8]| {
+            let mut mac = hmac(secret);
+            mac.update(purpose);
+            mac.finalize().into_bytes().into()
+        };
+
+        CookieKey {
+            signing: derive(b"hyperion cookie signing"),
+            encryption: derive(b"hyperion cookie encryption"),
+        }
+    }
+
+    /// A random key, which invalidates every cookie once the server restarts
+    pub fn generate() -> CookieKey {
+        let mut secret = [0; KEY_LENGTH];
+        rand::thread_rng().fill_bytes(&mut secret);
+        CookieKey::from_secret(&secret)
+    }
+
+    fn mac(&self, name: &str, value: &str) -> HmacSha256 {
+        let mut mac = hmac(&self.signing);
+        mac.update(name.as_bytes());
+        mac.update(b"=");
+        mac.update(value.as_bytes());
+        mac
+    }
+
+    fn cipher(&self) -> XChaCha20Poly1305 {
+        XChaCha20Poly1305::new(Key::from_slice(&self.encryption))
+    }
+}
+
+fn hmac(key: &[u8]) -> HmacSha256 {
+    <HmacSha256 as Mac>::new_from_slice(key).expect("HMAC accepts keys of any length")
+}
+
+impl Debug for CookieKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("CookieKey(..)")
+    }
+}
+
+/// The keys used for signed and private cookies.
+///
+/// New cookies are always signed and encrypted with the primary key, while cookies sent by
+/// clients are checked against every key, so a secret can be rotated by making the new key
+/// primary and keeping the old one as a fallback until its cookies have expired.
+///
+/// Register the keys with [`HttpServerBuilder::cookie_keys`](crate::HttpServerBuilder::cookie_keys)
+/// to use [`HttpRequest::signed_cookies`](crate::HttpRequest::signed_cookies) and
+/// [`HttpRequest::private_cookies`](crate::HttpRequest::private_cookies).
+#[derive(Debug, Clone)]
+pub struct CookieKeys {
+    primary: CookieKey,
+    fallbacks: Vec<CookieKey>,
+}
+
+impl CookieKeys {
+    pub fn new(primary: CookieKey) -> CookieKeys {
+        CookieKeys {
+            primary,
+            fallbacks: vec![],
+        }
+    }
+
+    /// Also accepts cookies signed or encrypted with `key`
+    pub fn with_fallback(mut self, key: CookieKey) -> CookieKeys {
+        self.fallbacks.push(key);
+        self
+    }
+
+    fn keys(&self) -> impl Iterator<Item = &CookieKey> {
+        std::iter::once(&self.primary).chain(&self.fallbacks)
+    }
+
+    /// A copy of `cookie` whose value is prefixed with an HMAC-SHA256 of its name and value
+    pub fn sign(&self, cookie: &HttpCookie) -> HttpCookie {
+        let signature = self.primary.mac(cookie.name(), cookie.value());
+        let signature = URL_SAFE_NO_PAD.encode(signature.finalize().into_bytes());
+
+        cookie.with_value(format!("{signature}{}", cookie.value()))
+    }
+
+    /// The original cookie, if `cookie` was signed with any of the keys
+    pub fn verify(&self, cookie: &HttpCookie) -> Option<HttpCookie> {
+        let value = cookie.value();
+
+        if !value.is_char_boundary(SIGNATURE_LENGTH) {
+            return None;
+        }
+
+        let (signature, value) = value.split_at(SIGNATURE_LENGTH);
+        let signature = URL_SAFE_NO_PAD.decode(signature).ok()?;
+
+        self.keys()
+            .any(|key| {
+                key.mac(cookie.name(), value)
+                    .verify_slice(&signature)
+                    .is_ok()
+            })
+            .then(|| cookie.with_value(value.to_string()))
+    }
+
+    /// A copy of `cookie` whose value is encrypted and authenticated, so the client can
+    /// neither read nor change it
+    pub fn encrypt(&self, cookie: &HttpCookie) -> HttpCookie {
+        let mut nonce = [0; NONCE_LENGTH];
+        rand::thread_rng().fill_bytes(&mut nonce);
+
+        let payload = Payload {
+            msg: cookie.value().as_bytes(),
+            aad: cookie.name().as_bytes(),
+        };
+
+        let mut sealed = nonce.to_vec();
+        sealed.extend(
+            self.primary
+                .cipher()
+                .encrypt(XNonce::from_slice(&nonce), payload)
+                .expect("Encrypting a cookie value cannot fail"),
+        );
+
+        cookie.with_value(URL_SAFE_NO_PAD.encode(sealed))
+    }
+
+    /// The original cookie, if `cookie` was encrypted with any of the keys
+    pub fn decrypt(&self, cookie: &HttpCookie) -> Option<HttpCookie> {
+        let sealed = URL_SAFE_NO_PAD.decode(cookie.value()).ok()?;
+
+        if sealed.len() < NONCE_LENGTH {
+            return None;
+        }
+
+        let (nonce, ciphertext) = sealed.split_at(NONCE_LENGTH);
+
+        let value = self.keys().find_map(|key| {
+            let payload = Payload {
+                msg: ciphertext,
+                aad: cookie.name().as_bytes(),
+            };
+
+            key.cipher()
+                .decrypt(XNonce::from_slice(nonce), payload)
+                .ok()
+        })?;
+
+        String::from_utf8(value)
+            .ok()
+            .map(|value| cookie.with_value(value))
+    }
+}
+
+/// The cookies of a request whose signature could be verified
+pub struct SignedCookieJar<'a> {
+    keys: &'a CookieKeys,
+    cookies: Vec<HttpCookie>,
+}
+
+impl<'a> SignedCookieJar<'a> {
+    pub(crate) fn new(keys: &'a CookieKeys, cookies: Vec<HttpCookie>) -> SignedCookieJar<'a> {
+        SignedCookieJar { keys, cookies }
+    }
+
+    /// The cookie called `name` with its signature removed, if its signature is valid
+    pub fn get(&self, name: &str) -> Option<HttpCookie> {
+        self.cookies
+            .iter()
+            .filter(|cookie| cookie.name() == name)
+            .find_map(|cookie| self.keys.verify(cookie))
+    }
+
+    /// Signs `cookie` so it can be added to a response with
+    /// [`HttpResponse::set_cookie`](crate::HttpResponse::set_cookie)
+    pub fn sign(&self, cookie: &HttpCookie) -> HttpCookie {
+        self.keys.sign(cookie)
+    }
+}
+
+/// The cookies of a request that could be decrypted
+pub struct PrivateCookieJar<'a> {
+    keys: &'a CookieKeys,
+    cookies: Vec<HttpCookie>,
+}
+
+impl<'a> PrivateCookieJar<'a> {
+    pub(crate) fn new(keys: &'a CookieKeys, cookies: Vec<HttpCookie>) -> PrivateCookieJar<'a> {
+        PrivateCookieJar { keys, cookies }
+    }
+
+    /// The decrypted cookie called `name`, if it was encrypted with one of the keys
+    pub fn get(&self, name: &str) -> Option<HttpCookie> {
+        self.cookies
+            .iter()
+            .filter(|cookie| cookie.name() == name)
+            .find_map(|cookie| self.keys.decrypt(cookie))
+    }
+
+    /// Encrypts `cookie` so it can be added to a response with
+    /// [`HttpResponse::set_cookie`](crate::HttpResponse::set_cookie)
+    pub fn encrypt(&self, cookie: &HttpCookie) -> HttpCookie {
+        self.keys.encrypt(cookie)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cookie(value: &str) -> HttpCookie {
+        HttpCookie::builder("session", value).build().unwrap()
+    }
+
+    fn key(byte: u8) -> CookieKey {
+        CookieKey::from_secret(&[byte; KEY_LENGTH])
+    }
+
+    #[test]
+    fn verifies_signed_cookies() {
+        let keys = CookieKeys::new(key(1));
+        let signed = keys.sign(&cookie("user=42"));
+
+        assert_ne!(signed.value(), "user=42");
+        assert_eq!(keys.verify(&signed).unwrap().value(), "user=42");
+    }
+
+    #[test]
+    fn rejects_tampered_signatures() {
+        let keys = CookieKeys::new(key(1));
+        let signed = keys.sign(&cookie("user=42"));
+        let (signature, _) = signed.value().split_at(SIGNATURE_LENGTH);
+
+        assert!(keys
+            .verify(&cookie(&format!("{signature}user=43")))
+            .is_none());
+        assert!(keys.verify(&cookie("user=42")).is_none());
+        assert!(keys.verify(&cookie("")).is_none());
+
+        let renamed = HttpCookie::builder("other", signed.value())
+            .build()
+            .unwrap();
+        assert!(keys.verify(&renamed).is_none());
+    }
+
+    #[test]
+    fn verifies_cookies_signed_with_a_rotated_key() {
+        let old_keys = CookieKeys::new(key(1));
+        let rotated = CookieKeys::new(key(2)).with_fallback(key(1));
+        let signed = old_keys.sign(&cookie("user=42"));
+
+        assert_eq!(rotated.verify(&signed).unwrap().value(), "user=42");
+        assert!(CookieKeys::new(key(2)).verify(&signed).is_none());
+
+        // New cookies are signed with the primary key only
+        assert!(old_keys.verify(&rotated.sign(&cookie("user=42"))).is_none());
+    }
+
+    #[test]
+    fn decrypts_private_cookies() {
+        let keys = CookieKeys::new(key(1));
+        let encrypted = keys.encrypt(&cookie("user=42"));
+
+        assert!(!encrypted.value().contains("42"));
+        assert_ne!(encrypted.value(), keys.encrypt(&cookie("user=42")).value());
+        assert_eq!(keys.decrypt(&encrypted).unwrap().value(), "user=42");
+    }
+
+    #[test]
+    fn rejects_tampered_private_cookies() {
+        let keys = CookieKeys::new(key(1));
+        let encrypted = keys.encrypt(&cookie("user=42"));
+
+        let mut sealed = URL_SAFE_NO_PAD.decode(encrypted.value()).unwrap();
+        *sealed.last_mut().unwrap() ^= 1;
+        let tampered = cookie(&URL_SAFE_NO_PAD.encode(sealed));
+
+        assert!(keys.decrypt(&tampered).is_none());
+        assert!(keys.decrypt(&cookie("not+base64")).is_none());
+        assert!(keys.decrypt(&cookie("c2hvcnQ")).is_none());
+
+        let renamed = HttpCookie::builder("other", encrypted.value())
+            .build()
+            .unwrap();
+        assert!(keys.decrypt(&renamed).is_none());
+    }
+
+    #[test]
+    fn decrypts_cookies_encrypted_with_a_rotated_key() {
+        let encrypted = CookieKeys::new(key(1)).encrypt(&cookie("user=42"));
+        let rotated = CookieKeys::new(key(2)).with_fallback(key(1));
+
+        assert_eq!(rotated.decrypt(&encrypted).unwrap().value(), "user=42");
+        assert!(CookieKeys::new(key(2)).decrypt(&encrypted).is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "at least 32 bytes")]
+    fn rejects_short_secrets() {
+        CookieKey::from_secret(b"too short");
+    }
+}
@@ -0,0 +1,189 @@
+use chrono::{DateTime, NaiveDateTime, TimeZone, Utc};
+use std::{
+    cell::RefCell,
+    sync::{
+        atomic::{AtomicI64, Ordering},
+        Mutex, OnceLock,
+    },
+    time::Duration,
+};
+
+/// `IMF-fixdate` from RFC 9110, section 5.6.7, e.g. `Sun, 06 Nov 1994 08:49:37 GMT`
+const IMF_FIXDATE_FORMAT: &str = "%a, %d %b %Y %H:%M:%S GMT";
+/// The obsolete RFC 850 format, e.g. `Sunday, 06-Nov-94 08:49:37 GMT`
+const RFC_850_FORMAT: &str = "%A, %d-%b-%y %H:%M:%S GMT";
+/// The obsolete format of C's `asctime()`, e.g. `Sun Nov  6 08:49:37 1994`, once the padding
+/// of the day has been collapsed
+const ASCTIME_FORMAT: &str = "%a %b %d %H:%M:%S %Y";
+
+/// The `Date` of the current second, formatted once per second by a background thread
+static DATE_CLOCK: OnceLock<DateClock> = OnceLock::new();
+
+thread_local! {
+    /// A copy of the date of [`DATE_CLOCK`] with the second it was rendered for, so that a
+    /// thread only takes the lock once per second
+    static CACHED_DATE: RefCell<(i64, String)> = const { RefCell::new((i64::MIN, String::new())) };
+}
+
+struct DateClock {
+    /// The second `date` was rendered for, updated after `date`
+    second: AtomicI64,
+    date: Mutex<String>,
+}
+
+impl DateClock {
+    /// Renders the current date and starts the thread refreshing it at every second
+    fn start() -> DateClock {
+        std::thread::Builder::new()
+            .name("hyperion-date".to_string())
+            .spawn(|| loop {
+                let now = Utc::now();
+                let until_next_second =
+                    1_000_000_000 - now.timestamp_subsec_nanos() % 1_000_000_000;
+                std::thread::sleep(Duration::from_nanos(until_next_second.into()));
+
+                if let Some(clock) = DATE_CLOCK.get() {
+                    clock.update(Utc::now());
+                }
+            })
+            .expect("Failed to spawn the thread rendering the Date header");
+
+        DateClock::new(Utc::now())
+    }
+
+    fn new(now: DateTime<Utc>) -> DateClock {
+        DateClock {
+            second: AtomicI64::new(now.timestamp()),
+            date: Mutex::new(format_http_date(now)),
+        }
+    }
+
+    fn update(&self, now: DateTime<Utc>) {
+        *self
+            .date
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = format_http_date(now);
+        self.second.store(now.timestamp(), Ordering::Release);
+    }
+
+    /// The date of this clock, through the copy cached by the current thread
+    fn read(&self) -> String {
+        let second = self.second.load(Ordering::Acquire);
+
+        CACHED_DATE.with(|cached| {
+            let mut cached = cached.borrow_mut();
+
+            if cached.0 != second {
+                let date = self
+                    .date
+                    .lock()
+                    .unwrap_or_else(|poisoned| poisoned.into_inner());
+                *cached = (second, date.clone());
+            }
+
+            cached.1.clone()
+        })
+    }
+}
+
+/// Formats `date` as an `IMF-fixdate`, the format HTTP uses for dates
+pub fn format_http_date(date: DateTime<Utc>) -> String {
+    date.format(IMF_FIXDATE_FORMAT).to_string()
+}
+
+/// Parses an HTTP date in the `IMF-fixdate` format or either of the obsolete formats
+/// recipients must still accept
+pub fn parse_http_date(value: &str) -> Option<DateTime<Utc>> {
+    let value = value.trim();
+    let parse = |value: &str, format| NaiveDateTime::parse_from_str(value, format).ok();
+
+    parse(value, IMF_FIXDATE_FORMAT)
+        .or_else(|| parse(value, RFC_850_FORMAT))
+        .or_else(|| {
+            let collapsed = value.split_whitespace().collect::<Vec<_>>().join(" ");
+            parse(&collapsed, ASCTIME_FORMAT)
+        })
+        .map(|date| Utc.from_utc_datetime(&date))
+}
+
+/// The current date as an `IMF-fixdate`, for the `Date` header of responses.
+///
+/// Neither reads the clock nor takes a lock, unless the date changed since this thread last
+/// read it.
+pub(crate) fn current_http_date() -> String {
+    DATE_CLOCK.get_or_init(DateClock::start).read()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(timestamp: i64) -> DateTime<Utc> {
+        Utc.timestamp_opt(timestamp, 0).unwrap()
+    }
+
+    #[test]
+    fn parses_every_http_date_format() {
+        let expected = date(784111777);
+
+        for value in [
+            "Sun, 06 Nov 1994 08:49:37 GMT",
+            "Sunday, 06-Nov-94 08:49:37 GMT",
+            "Sun Nov  6 08:49:37 1994",
+            "Sun Nov 06 08:49:37 1994",
+            " Sun, 06 Nov 1994 08:49:37 GMT ",
+        ] {
+            assert_eq!(parse_http_date(value), Some(expected), "{value:?}");
+        }
+    }
+
+    #[test]
+    fn round_trips_http_dates() {
+        for timestamp in [0, 784111777, 1445412480, 4102444799] {
+            let formatted = format_http_date(date(timestamp));
+            assert_eq!(parse_http_date(&formatted), Some(date(timestamp)));
+        }
+
+        assert_eq!(format_http_date(date(0)), "Thu, 01 Jan 1970 00:00:00 GMT");
+        assert_eq!(
+            format_http_date(date(784111777)),
+            "Sun, 06 Nov 1994 08:49:37 GMT"
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_http_dates() {
+        for value in [
+            "",
+            "yesterday",
+            "Sun, 06 Nov 1994 08:49:37",
+            "Sun, 06 Nov 1994 08:49:37 UTC",
+            "Sun, 06 Nov 1994 08:49:37 +0000",
+            "Sun, 31 Nov 1994 08:49:37 GMT",
+            "Sun, 06 Nov 1994 24:49:37 GMT",
+            "Sun, 06 Foo 1994 08:49:37 GMT",
+            "1994-11-06T08:49:37Z",
+        ] {
+            assert_eq!(parse_http_date(value), None, "{value:?}");
+        }
+    }
+
+    #[test]
+    fn current_date_is_an_imf_fixdate_of_now() {
+        let date = parse_http_date(&current_http_date()).expect("a valid IMF-fixdate");
+        assert!((Utc::now() - date).num_seconds().abs() <= 2);
+    }
+
+    #[test]
+    fn clock_renders_each_second_once() {
+        let clock = DateClock::new(date(784111777));
+        assert_eq!(clock.read(), "Sun, 06 Nov 1994 08:49:37 GMT");
+
+        // Within the same second the copy cached by this thread is used
+        *clock.date.lock().unwrap() = "changed".to_string();
+        assert_eq!(clock.read(), "Sun, 06 Nov 1994 08:49:37 GMT");
+
+        clock.update(date(784111778));
+        assert_eq!(clock.read(), "Sun, 06 Nov 1994 08:49:38 GMT");
+    }
+}
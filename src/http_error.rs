@@ -1,4 +1,4 @@
-use crate::HttpResponse;
+use crate::{HttpResponse, StatusCode};
 use std::fmt::Display;
 use std::io;
 use thiserror::Error;
@@ -6,15 +6,33 @@ use thiserror::Error;
 #[derive(Error, Debug)]
 pub enum HttpError {
     BadRequest { message: String },
+    UnsupportedMediaType { message: String },
+    PayloadTooLarge { message: String },
+    UriTooLong { message: String },
+    RequestHeaderFieldsTooLarge { message: String },
+    InternalServerError { message: String },
+    NotImplemented { message: String },
+    HttpVersionNotSupported { message: String },
     IoError(#[from] io::Error),
 }
 
 impl HttpError {
     pub fn as_response(&self) -> HttpResponse {
-        match self {
-            HttpError::BadRequest { message } => HttpResponse::new(400, None),
-            HttpError::IoError(_) => HttpResponse::new(500, None),
-        }
+        let status = match self {
+            HttpError::BadRequest { .. } => StatusCode::BAD_REQUEST,
+            HttpError::UnsupportedMediaType { .. } => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            HttpError::PayloadTooLarge { .. } => StatusCode::CONTENT_TOO_LARGE,
+            HttpError::UriTooLong { .. } => StatusCode::URI_TOO_LONG,
+            HttpError::RequestHeaderFieldsTooLarge { .. } => {
+                StatusCode::REQUEST_HEADER_FIELDS_TOO_LARGE
+            }
+            HttpError::InternalServerError { .. } => StatusCode::INTERNAL_SERVER_ERROR,
+            HttpError::NotImplemented { .. } => StatusCode::NOT_IMPLEMENTED,
+            HttpError::HttpVersionNotSupported { .. } => StatusCode::HTTP_VERSION_NOT_SUPPORTED,
+            HttpError::IoError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+
+        HttpResponse::new(status, None)
     }
 }
 
@@ -22,6 +40,13 @@ impl Display for HttpError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             HttpError::BadRequest { message } => write!(f, "{message}"),
+            HttpError::UnsupportedMediaType { message } => write!(f, "{message}"),
+            HttpError::PayloadTooLarge { message } => write!(f, "{message}"),
+            HttpError::UriTooLong { message } => write!(f, "{message}"),
+            HttpError::RequestHeaderFieldsTooLarge { message } => write!(f, "{message}"),
+            HttpError::InternalServerError { message } => write!(f, "{message}"),
+            HttpError::NotImplemented { message } => write!(f, "{message}"),
+            HttpError::HttpVersionNotSupported { message } => write!(f, "{message}"),
             HttpError::IoError(error) => write!(f, "{error}"),
         }
     }
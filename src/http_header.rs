@@ -1,30 +1,80 @@
+use crate::HttpError;
 use std::fmt::Display;
 
+/// A header field whose name is a token and whose value has no control characters, so it can
+/// never inject another header or end the header section when written
 #[derive(Debug, Clone)]
 pub struct HttpHeader {
-    pub name: String,
-    pub value: String,
+    name: String,
+    value: String,
 }
 
 impl HttpHeader {
-    pub fn new(name: String, value: String) -> HttpHeader {
-        HttpHeader { name, value }
+    /// Fails with [`HttpError::InternalServerError`] if `name` is not a token or `value`
+    /// contains control characters such as CR, LF or NUL, as an invalid header built for a
+    /// response is a bug of the server rather than of the client
+    pub fn new(name: &str, value: &str) -> Result<HttpHeader, HttpError> {
+        HttpHeader::validate(name, value)
+            .map_err(|message| HttpError::InternalServerError { message })
     }
 
-    pub fn from(header_line: &str) -> Option<HttpHeader> {
-        let mut header = header_line.split(": ");
+    fn validate(name: &str, value: &str) -> Result<HttpHeader, String> {
+        if !is_token(name) {
+            return Err(format!("Invalid header name `{}`", name.escape_debug()));
+        }
+
+        if !is_field_value(value) {
+            return Err(format!("Invalid value for header `{name}`"));
+        }
+
+        Ok(HttpHeader::new_unchecked(name, value.to_string()))
+    }
 
-        let header_name = header.next();
-        let header_value = header.next();
+    /// Builds a header from a name and value that are known to be valid.
+    ///
+    /// # Panics
+    ///
+    /// Panics if they are not, rather than letting a line break split the header.
+    pub(crate) fn new_unchecked(name: &str, value: String) -> HttpHeader {
+        assert!(
+            is_token(name) && is_field_value(&value),
+            "Invalid header `{}`",
+            name.escape_debug()
+        );
 
-        match (header_name, header_value) {
-            (Some(name), Some(value)) => Some(HttpHeader {
-                name: name.to_string(),
-                value: value.to_string(),
-            }),
-            _ => None,
+        HttpHeader {
+            name: name.to_string(),
+            value,
         }
     }
+
+    /// Parses a `field-line` as defined by RFC 9112, section 5.
+    ///
+    /// Fails with [`HttpError::BadRequest`] if the line has no colon, the name is not a token
+    /// (which includes whitespace before the colon), the line is an obsolete line folding or
+    /// the value contains control characters such as a bare CR, LF or NUL.
+    pub fn parse(header_line: &str) -> Result<HttpHeader, HttpError> {
+        if header_line.starts_with([' ', '\t']) {
+            return Err(HttpError::BadRequest {
+                message: "Obsolete line folding in header".to_string(),
+            });
+        }
+
+        let (name, value) = header_line.split_once(':').ok_or(HttpError::BadRequest {
+            message: "Header line without a colon".to_string(),
+        })?;
+
+        HttpHeader::validate(name, value.trim_matches([' ', '\t']))
+            .map_err(|message| HttpError::BadRequest { message })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn value(&self) -> &str {
+        &self.value
+    }
 }
 
 impl Display for HttpHeader {
@@ -32,3 +82,90 @@ impl Display for HttpHeader {
         write!(f, "{}: {}", self.name, self.value)
     }
 }
+
+/// Whether `value` is a non-empty `token` as defined by RFC 9110, section 5.6.2
+pub(crate) fn is_token(value: &str) -> bool {
+    !value.is_empty()
+        && value
+            .bytes()
+            .all(|byte| byte.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&byte))
+}
+
+/// Whether `value` only contains visible characters, spaces, tabs and `obs-text`, as allowed in
+/// a `field-value` by RFC 9110, section 5.5
+pub(crate) fn is_field_value(value: &str) -> bool {
+    value
+        .bytes()
+        .all(|byte| byte == b'\t' || byte == b' ' || (byte > 0x20 && byte != 0x7f))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn is_bad_request(line: &str) -> bool {
+        matches!(HttpHeader::parse(line), Err(HttpError::BadRequest { .. }))
+    }
+
+    #[test]
+    fn parses_field_lines() {
+        let header = HttpHeader::parse("Content-Type: \t text/html; charset=utf-8 \t").unwrap();
+        assert_eq!(header.name(), "Content-Type");
+        assert_eq!(header.value(), "text/html; charset=utf-8");
+
+        let header = HttpHeader::parse("X-Empty:").unwrap();
+        assert_eq!(header.value(), "");
+
+        let header = HttpHeader::parse("Host: localhost:8080").unwrap();
+        assert_eq!(header.value(), "localhost:8080");
+    }
+
+    #[test]
+    fn rejects_obsolete_line_folding() {
+        assert!(is_bad_request(" continued value"));
+        assert!(is_bad_request("\tcontinued value"));
+    }
+
+    #[test]
+    fn rejects_lines_without_a_colon() {
+        assert!(is_bad_request("Content-Type text/html"));
+        assert!(is_bad_request(""));
+    }
+
+    #[test]
+    fn rejects_invalid_names() {
+        assert!(is_bad_request("Content-Type : text/html"));
+        assert!(is_bad_request(": text/html"));
+        assert!(is_bad_request("Content Type: text/html"));
+        assert!(is_bad_request("Content(Type): text/html"));
+        assert!(is_bad_request("Cönt: text/html"));
+    }
+
+    #[test]
+    fn rejects_control_characters_in_values() {
+        assert!(is_bad_request("X-Test: a\rb"));
+        assert!(is_bad_request("X-Test: a\nInjected: b"));
+        assert!(is_bad_request("X-Test: a\0b"));
+        assert!(is_bad_request("X-Test: a\x7fb"));
+
+        // obs-text is allowed
+        assert!(HttpHeader::parse("X-Test: caf\u{e9}").is_ok());
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid header")]
+    fn refuses_line_breaks_in_unchecked_headers() {
+        HttpHeader::new_unchecked("Allow", "GET\r\nInjected: 1".to_string());
+    }
+
+    #[test]
+    fn rejects_requests_with_malformed_headers() {
+        let request = |headers: &str| {
+            crate::HttpRequest::new(format!("GET / HTTP/1.1\r\n{headers}").into_bytes())
+        };
+
+        assert!(request("Host: localhost\r\nAccept: */*").is_ok());
+        assert!(request("Host: localhost\r\n folded").is_err());
+        assert!(request("Host localhost").is_err());
+    }
+}
@@ -0,0 +1,230 @@
+use crate::{HttpError, HttpHeader, TypedHeader};
+use std::fmt::Display;
+
+/// The headers of a request or response.
+///
+/// Names are matched case-insensitively, while the order and case in which headers were
+/// added, and any repeated names, are preserved.
+#[derive(Debug, Clone, Default)]
+pub struct HttpHeaderMap {
+    headers: Vec<HttpHeader>,
+}
+
+impl HttpHeaderMap {
+    pub fn new() -> HttpHeaderMap {
+        HttpHeaderMap::default()
+    }
+
+    /// The value of the first header named `name`
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|header| header.name().eq_ignore_ascii_case(name))
+            .map(|header| header.value())
+    }
+
+    /// The values of every header named `name`, in order
+    pub fn get_all<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+        self.headers
+            .iter()
+            .filter(move |header| header.name().eq_ignore_ascii_case(name))
+            .map(|header| header.value())
+    }
+
+    /// Parses the first header named [`T::NAME`](TypedHeader::NAME), failing with
+    /// [`HttpError::BadRequest`] if it is malformed
+    pub fn get_typed<T: TypedHeader>(&self) -> Result<Option<T>, HttpError> {
+        self.get(T::NAME).map(T::decode).transpose()
+    }
+
+    /// Replaces every header named [`T::NAME`](TypedHeader::NAME) with `header`, failing
+    /// with [`HttpError::InternalServerError`] if its encoded value is not a valid header
+    /// value
+    pub fn insert_typed<T: TypedHeader>(&mut self, header: &T) -> Result<(), HttpError> {
+        self.insert(HttpHeader::new(T::NAME, &header.encode())?);
+        Ok(())
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.get(name).is_some()
+    }
+
+    /// Replaces every header with the same name as `header`.
+    ///
+    /// The header takes the place of the first one it replaces, or is added last if there
+    /// was none.
+    pub fn insert(&mut self, header: HttpHeader) {
+        let name = header.name().to_string();
+        let mut header = Some(header);
+
+        self.headers.retain_mut(|existing| {
+            if !existing.name().eq_ignore_ascii_case(&name) {
+                return true;
+            }
+
+            match header.take() {
+                Some(header) => {
+                    *existing = header;
+                    true
+                }
+                None => false,
+            }
+        });
+
+        self.headers.extend(header);
+    }
+
+    /// Adds `header` last, keeping any header with the same name
+    pub fn append(&mut self, header: HttpHeader) {
+        self.headers.push(header);
+    }
+
+    /// Removes every header named `name`, returning them in order
+    pub fn remove(&mut self, name: &str) -> Vec<HttpHeader> {
+        let (removed, kept) = std::mem::take(&mut self.headers)
+            .into_iter()
+            .partition(|header| header.name().eq_ignore_ascii_case(name));
+
+        self.headers = kept;
+        removed
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, HttpHeader> {
+        self.headers.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.headers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.headers.is_empty()
+    }
+}
+
+impl<'a> IntoIterator for &'a HttpHeaderMap {
+    type Item = &'a HttpHeader;
+    type IntoIter = std::slice::Iter<'a, HttpHeader>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl IntoIterator for HttpHeaderMap {
+    type Item = HttpHeader;
+    type IntoIter = std::vec::IntoIter<HttpHeader>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.headers.into_iter()
+    }
+}
+
+impl FromIterator<HttpHeader> for HttpHeaderMap {
+    fn from_iter<I: IntoIterator<Item = HttpHeader>>(iter: I) -> Self {
+        HttpHeaderMap {
+            headers: iter.into_iter().collect(),
+        }
+    }
+}
+
+impl Extend<HttpHeader> for HttpHeaderMap {
+    fn extend<I: IntoIterator<Item = HttpHeader>>(&mut self, iter: I) {
+        self.headers.extend(iter);
+    }
+}
+
+impl Display for HttpHeaderMap {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for header in &self.headers {
+            write!(f, "{header}\r\n")?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header(name: &str, value: &str) -> HttpHeader {
+        HttpHeader::new(name, value).unwrap()
+    }
+
+    fn headers() -> HttpHeaderMap {
+        HttpHeaderMap::from_iter([
+            header("Set-Cookie", "a=1"),
+            header("Content-Type", "text/plain"),
+            header("set-cookie", "b=2"),
+            header("SET-COOKIE", "c=3"),
+        ])
+    }
+
+    fn names_and_values(headers: &HttpHeaderMap) -> Vec<String> {
+        headers.iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn gets_headers_case_insensitively() {
+        let headers = headers();
+
+        assert_eq!(headers.get("content-type"), Some("text/plain"));
+        assert_eq!(headers.get("CONTENT-TYPE"), Some("text/plain"));
+        assert_eq!(headers.get("Set-Cookie"), Some("a=1"));
+        assert_eq!(headers.get("Content-Length"), None);
+        assert!(headers.contains("set-COOKIE"));
+        assert!(!headers.contains("Content-Length"));
+    }
+
+    #[test]
+    fn gets_every_value_in_order() {
+        let headers = headers();
+
+        assert_eq!(
+            headers.get_all("set-cookie").collect::<Vec<_>>(),
+            ["a=1", "b=2", "c=3"]
+        );
+        assert_eq!(headers.get_all("Content-Length").count(), 0);
+    }
+
+    #[test]
+    fn insert_replaces_every_value() {
+        let mut headers = headers();
+        headers.insert(header("set-cookie", "d=4"));
+
+        assert_eq!(
+            names_and_values(&headers),
+            ["set-cookie: d=4", "Content-Type: text/plain"]
+        );
+
+        headers.insert(header("Content-Length", "0"));
+        assert_eq!(headers.len(), 3);
+        assert_eq!(headers.iter().last().unwrap().name(), "Content-Length");
+    }
+
+    #[test]
+    fn append_keeps_existing_values() {
+        let mut headers = headers();
+        headers.append(header("Set-Cookie", "d=4"));
+
+        assert_eq!(headers.get_all("Set-Cookie").count(), 4);
+        assert_eq!(headers.get("Set-Cookie"), Some("a=1"));
+    }
+
+    #[test]
+    fn removes_every_value() {
+        let mut headers = headers();
+
+        let removed = headers.remove("SET-cookie");
+        assert_eq!(
+            removed.iter().map(HttpHeader::value).collect::<Vec<_>>(),
+            ["a=1", "b=2", "c=3"]
+        );
+        assert_eq!(names_and_values(&headers), ["Content-Type: text/plain"]);
+
+        assert!(headers.remove("Set-Cookie").is_empty());
+        headers.remove("content-type");
+        assert!(headers.is_empty());
+    }
+}
@@ -1,41 +1,133 @@
-use crate::HttpError;
+use crate::{http_header::is_token, HttpError};
 use std::fmt::Display;
 
-#[derive(Debug, Clone, Copy, Default)]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
 pub enum HttpMethod {
     #[default]
     GET,
+    HEAD,
     POST,
     PUT,
     DELETE,
-    HEAD,
+    CONNECT,
+    OPTIONS,
+    TRACE,
+    PATCH,
+    /// Any other method, e.g. `PROPFIND` from WebDAV
+    Extension(String),
 }
 
 impl HttpMethod {
+    /// Parses a method name, which is case-sensitive, so `get` is an extension method rather
+    /// than `GET`. Fails with [`HttpError::BadRequest`] if the name is not a token.
     pub fn new(method_string: &str) -> Result<HttpMethod, HttpError> {
-        match method_string.to_uppercase().as_str() {
+        match method_string {
             "GET" => Ok(HttpMethod::GET),
+            "HEAD" => Ok(HttpMethod::HEAD),
             "POST" => Ok(HttpMethod::POST),
-            "UPDATE" => Ok(HttpMethod::PUT),
+            "PUT" => Ok(HttpMethod::PUT),
             "DELETE" => Ok(HttpMethod::DELETE),
-            "HEAD" => Ok(HttpMethod::HEAD),
+            "CONNECT" => Ok(HttpMethod::CONNECT),
+            "OPTIONS" => Ok(HttpMethod::OPTIONS),
+            "TRACE" => Ok(HttpMethod::TRACE),
+            "PATCH" => Ok(HttpMethod::PATCH),
+            method if is_token(method) => Ok(HttpMethod::Extension(method.to_string())),
             _ => Err(HttpError::BadRequest {
-                message: "Method Not allowed".to_string(),
+                message: "Invalid Method".to_string(),
             }),
         }
     }
-}
 
-impl Display for HttpMethod {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let method_string = match self {
+    pub fn as_str(&self) -> &str {
+        match self {
             HttpMethod::GET => "GET",
+            HttpMethod::HEAD => "HEAD",
             HttpMethod::POST => "POST",
             HttpMethod::PUT => "PUT",
             HttpMethod::DELETE => "DELETE",
-            HttpMethod::HEAD => "HEAD",
-        };
+            HttpMethod::CONNECT => "CONNECT",
+            HttpMethod::OPTIONS => "OPTIONS",
+            HttpMethod::TRACE => "TRACE",
+            HttpMethod::PATCH => "PATCH",
+            HttpMethod::Extension(method) => method,
+        }
+    }
+
+    /// Whether the method is read-only, as defined by RFC 9110, section 9.2.1
+    pub fn is_safe(&self) -> bool {
+        matches!(
+            self,
+            HttpMethod::GET | HttpMethod::HEAD | HttpMethod::OPTIONS | HttpMethod::TRACE
+        )
+    }
+
+    /// Whether repeating the request has the same effect as sending it once, as defined by
+    /// RFC 9110, section 9.2.2
+    pub fn is_idempotent(&self) -> bool {
+        self.is_safe() || matches!(self, HttpMethod::PUT | HttpMethod::DELETE)
+    }
+}
+
+impl Display for HttpMethod {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_standard_methods() {
+        let methods = [
+            ("GET", HttpMethod::GET),
+            ("HEAD", HttpMethod::HEAD),
+            ("POST", HttpMethod::POST),
+            ("PUT", HttpMethod::PUT),
+            ("DELETE", HttpMethod::DELETE),
+            ("CONNECT", HttpMethod::CONNECT),
+            ("OPTIONS", HttpMethod::OPTIONS),
+            ("TRACE", HttpMethod::TRACE),
+            ("PATCH", HttpMethod::PATCH),
+        ];
+
+        for (name, method) in methods {
+            assert_eq!(HttpMethod::new(name).unwrap(), method);
+            assert_eq!(method.to_string(), name);
+        }
+    }
+
+    #[test]
+    fn parses_method_names_case_sensitively() {
+        for name in ["get", "Get", "PROPFIND", "M-SEARCH"] {
+            let method = HttpMethod::new(name).unwrap();
+
+            assert_eq!(method, HttpMethod::Extension(name.to_string()));
+            assert_eq!(method.to_string(), name);
+            assert_eq!(HttpMethod::new(&method.to_string()).unwrap(), method);
+        }
+    }
+
+    #[test]
+    fn rejects_methods_that_are_not_tokens() {
+        for name in ["", "GE T", "GET\r\n", "G(ET)", "GÉT"] {
+            assert!(
+                matches!(HttpMethod::new(name), Err(HttpError::BadRequest { .. })),
+                "{name:?}"
+            );
+        }
+    }
 
-        write!(f, "{method_string}")
+    #[test]
+    fn classifies_safe_and_idempotent_methods() {
+        assert!(HttpMethod::GET.is_safe());
+        assert!(HttpMethod::TRACE.is_safe());
+        assert!(!HttpMethod::PUT.is_safe());
+        assert!(HttpMethod::PUT.is_idempotent());
+        assert!(HttpMethod::DELETE.is_idempotent());
+        assert!(!HttpMethod::POST.is_idempotent());
+        assert!(!HttpMethod::PATCH.is_idempotent());
+        assert!(!HttpMethod::Extension("GET".to_string()).is_safe());
     }
 }
@@ -0,0 +1,66 @@
+use crate::{http_router::RequestHandler, HttpRequest, HttpResponse};
+use futures::{future::BoxFuture, Future};
+use std::sync::Arc;
+
+/// Code run around request handlers, e.g. for authentication, logging or header injection.
+///
+/// Middleware is attached at three levels, and runs outermost first in this order:
+///
+/// 1. Global middleware registered with [`HttpServerBuilder::middleware`], which sees every
+///    request, including ones that match no route.
+/// 2. Router middleware registered with [`HttpRouter::middleware`], which sees every request
+///    handed to that router, including ones that match none of its routes.
+/// 3. Route middleware registered with [`HttpRouter::route_middleware`], which sees requests
+///    matching that route pattern, including ones answered with a 405 or 501 because the
+///    route has no handler for their method.
+///
+/// Within a level, middleware registered first runs first. Each middleware decides whether
+/// to call [`Next::run`] and may change the request before it and the response after it.
+///
+/// [`HttpServerBuilder::middleware`]: crate::HttpServerBuilder::middleware
+/// [`HttpRouter::middleware`]: crate::HttpRouter::middleware
+/// [`HttpRouter::route_middleware`]: crate::HttpRouter::route_middleware
+pub trait Middleware: Send + Sync + 'static {
+    fn handle(&self, request: HttpRequest, next: Next) -> BoxFuture<'static, HttpResponse>;
+}
+
+impl<F, P> Middleware for F
+where
+    F: Fn(HttpRequest, Next) -> P + Send + Sync + 'static,
+    P: Future<Output = HttpResponse> + Send + 'static,
+{
+    fn handle(&self, request: HttpRequest, next: Next) -> BoxFuture<'static, HttpResponse> {
+        Box::pin(self(request, next))
+    }
+}
+
+/// The rest of the pipeline after the current middleware
+pub struct Next {
+    handler: RequestHandler,
+}
+
+impl Next {
+    pub async fn run(self, request: HttpRequest) -> HttpResponse {
+        self.handler.call(request).await
+    }
+}
+
+/// Wraps `handler` so that `middlewares` run around it, the first one outermost
+pub(crate) fn layer(
+    handler: RequestHandler,
+    middlewares: &[Arc<dyn Middleware>],
+) -> RequestHandler {
+    middlewares
+        .iter()
+        .rev()
+        .fold(handler, |handler, middleware| {
+            let middleware = middleware.clone();
+
+            RequestHandler::from_boxed(Arc::new(move |request| {
+                let next = Next {
+                    handler: handler.clone(),
+                };
+                middleware.handle(request, next)
+            }))
+        })
+}
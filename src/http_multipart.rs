@@ -0,0 +1,360 @@
+use crate::{
+    http_body_reader::from_io_error,
+    http_typed_header::{parse_parameters, split_unquoted},
+    HttpError, HttpHeader, HttpHeaderMap,
+};
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+const READ_CHUNK_SIZE: usize = 0x2000;
+
+/// Size limits enforced while parsing a `multipart/form-data` body
+#[derive(Debug, Clone, Copy)]
+pub struct MultipartLimits {
+    /// Maximum size of a single part, headers included
+    pub max_part_size: usize,
+    /// Maximum size of the whole body
+    pub max_total_size: usize,
+}
+
+impl Default for MultipartLimits {
+    fn default() -> Self {
+        MultipartLimits {
+            max_part_size: 1024 * 1024,
+            max_total_size: 16 * 1024 * 1024,
+        }
+    }
+}
+
+/// A single field or file of a `multipart/form-data` body
+#[derive(Debug, Clone)]
+pub struct MultipartPart {
+    name: String,
+    filename: Option<String>,
+    content_type: Option<String>,
+    headers: HttpHeaderMap,
+    bytes: Vec<u8>,
+}
+
+impl MultipartPart {
+    fn new(headers: HttpHeaderMap, bytes: Vec<u8>) -> Result<MultipartPart, HttpError> {
+        let find_header = |name: &str| headers.get(name).map(str::to_string);
+
+        let disposition = find_header("Content-Disposition").ok_or(HttpError::BadRequest {
+            message: "Multipart part without Content-Disposition".to_string(),
+        })?;
+
+        let parameters = parse_parameters(split_unquoted(&disposition, ';').into_iter().skip(1))
+            .ok_or(HttpError::BadRequest {
+                message: "Invalid Content-Disposition in multipart part".to_string(),
+            })?;
+
+        let find_parameter = |name: &str| {
+            parameters
+                .iter()
+                .find(|(existing, _)| existing == name)
+                .map(|(_, value)| value.clone())
+        };
+
+        Ok(MultipartPart {
+            name: find_parameter("name").ok_or(HttpError::BadRequest {
+                message: "Multipart part without a name".to_string(),
+            })?,
+            filename: find_parameter("filename"),
+            content_type: find_header("Content-Type"),
+            headers,
+            bytes,
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn filename(&self) -> Option<&str> {
+        self.filename.as_deref()
+    }
+
+    pub fn content_type(&self) -> Option<&str> {
+        self.content_type.as_deref()
+    }
+
+    pub fn headers(&self) -> &HttpHeaderMap {
+        &self.headers
+    }
+
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.bytes
+    }
+
+    /// The value of a plain field, failing with [`HttpError::BadRequest`] if it is not UTF-8
+    pub fn text(&self) -> Result<&str, HttpError> {
+        std::str::from_utf8(&self.bytes).map_err(|_| HttpError::BadRequest {
+            message: format!("Multipart field `{}` is not valid UTF-8", self.name),
+        })
+    }
+
+    /// Whether the part was sent as a file upload rather than a plain field
+    pub fn is_file(&self) -> bool {
+        self.filename.is_some()
+    }
+}
+
+/// An incremental `multipart/form-data` parser, reading one part at a time from `reader`
+pub struct Multipart<R> {
+    reader: R,
+    /// `\r\n--` followed by the boundary
+    delimiter: Vec<u8>,
+    limits: MultipartLimits,
+    /// Bytes read from `reader` but not yet parsed
+    buffer: Vec<u8>,
+    total_size: usize,
+    started: bool,
+    finished: bool,
+}
+
+impl<R: AsyncRead + Unpin> Multipart<R> {
+    pub fn new(reader: R, boundary: &str, limits: MultipartLimits) -> Multipart<R> {
+        Multipart {
+            reader,
+            delimiter: format!("\r\n--{boundary}").into_bytes(),
+            limits,
+            // Lets the first delimiter, which has no preceding line break, match like the others
+            buffer: b"\r\n".to_vec(),
+            total_size: 0,
+            started: false,
+            finished: false,
+        }
+    }
+
+    /// Reads the next part, returning `None` once the closing delimiter has been read
+    pub async fn next_part(&mut self) -> Result<Option<MultipartPart>, HttpError> {
+        if self.finished {
+            return Ok(None);
+        }
+
+        if !self.started {
+            // Discard the preamble
+            let delimiter = self.delimiter.clone();
+            self.read_until(&delimiter, self.limits.max_total_size)
+                .await?;
+            self.started = true;
+        }
+
+        while self.buffer.len() < 2 {
+            self.fill().await?;
+        }
+
+        if self.buffer.starts_with(b"--") {
+            self.finished = true;
+            return Ok(None);
+        }
+
+        // Discard any transport padding after the delimiter
+        let mut remaining = self.limits.max_part_size;
+        remaining -= self.read_until(b"\r\n", remaining).await?.len();
+
+        let mut headers = HttpHeaderMap::new();
+
+        loop {
+            let line = self.read_until(b"\r\n", remaining).await?;
+            remaining -= line.len();
+
+            if line.is_empty() {
+                break;
+            }
+
+            let line = String::from_utf8(line).map_err(|_| HttpError::BadRequest {
+                message: "Invalid UTF-8 Sequence in multipart headers".to_string(),
+            })?;
+
+            headers.append(HttpHeader::parse(&line)?);
+        }
+
+        let delimiter = self.delimiter.clone();
+        let bytes = self.read_until(&delimiter, remaining).await?;
+
+        MultipartPart::new(headers, bytes).map(Some)
+    }
+
+    /// Reads up to `pattern`, returning the bytes before it and discarding the pattern itself
+    async fn read_until(&mut self, pattern: &[u8], limit: usize) -> Result<Vec<u8>, HttpError> {
+        let mut searched = 0;
+
+        loop {
+            if let Some(index) = self.buffer[searched..]
+                .windows(pattern.len())
+                .position(|window| window == pattern)
+            {
+                if searched + index > limit {
+                    break;
+                }
+
+                let bytes = self.buffer.drain(..searched + index).collect();
+                self.buffer.drain(..pattern.len());
+                return Ok(bytes);
+            }
+
+            // The pattern may still start in the last `pattern.len() - 1` bytes
+            searched = self.buffer.len().saturating_sub(pattern.len() - 1);
+
+            if searched > limit {
+                break;
+            }
+
+            self.fill().await?;
+        }
+
+        Err(HttpError::PayloadTooLarge {
+            message: "Multipart part exceeds the size limit".to_string(),
+        })
+    }
+
+    async fn fill(&mut self) -> Result<(), HttpError> {
+        let mut chunk = [0; READ_CHUNK_SIZE];
+        let read_bytes_count = self.reader.read(&mut chunk).await.map_err(from_io_error)?;
+
+        if read_bytes_count == 0 {
+            return Err(HttpError::BadRequest {
+                message: "Unexpected end of multipart body".to_string(),
+            });
+        }
+
+        self.total_size += read_bytes_count;
+
+        if self.total_size > self.limits.max_total_size {
+            return Err(HttpError::PayloadTooLarge {
+                message: "Multipart body exceeds the size limit".to_string(),
+            });
+        }
+
+        self.buffer.extend(&chunk[..read_bytes_count]);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BODY: &[u8] = b"preamble\r\n\
+        --boundary\r\n\
+        Content-Disposition: form-data; name=\"title\"\r\n\
+        \r\n\
+        Hello\r\n\
+        --boundary\r\n\
+        Content-Disposition: form-data; name=\"upload\"; filename=\"a \\\"b\\\".txt\"\r\n\
+        Content-Type: text/plain\r\n\
+        \r\n\
+        line one\r\nline two\r\n\
+        --boundary--\r\n";
+
+    fn multipart(body: &'static [u8], limits: MultipartLimits) -> Multipart<&'static [u8]> {
+        Multipart::new(body, "boundary", limits)
+    }
+
+    #[tokio::test]
+    async fn parses_fields_and_files() {
+        let mut multipart = multipart(BODY, MultipartLimits::default());
+
+        let field = multipart.next_part().await.unwrap().unwrap();
+        assert_eq!(field.name(), "title");
+        assert_eq!(field.text().unwrap(), "Hello");
+        assert!(!field.is_file());
+
+        let file = multipart.next_part().await.unwrap().unwrap();
+        assert_eq!(file.name(), "upload");
+        assert_eq!(file.filename(), Some("a \"b\".txt"));
+        assert_eq!(file.content_type(), Some("text/plain"));
+        assert_eq!(file.bytes(), b"line one\r\nline two");
+
+        assert!(multipart.next_part().await.unwrap().is_none());
+        assert!(multipart.next_part().await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn keeps_separators_inside_quoted_parameters() {
+        let mut multipart = multipart(
+            b"--boundary\r\n\
+              Content-Disposition: form-data; name=\"upload\"; filename=\"a;b.txt\"\r\n\
+              \r\n\
+              data\r\n\
+              --boundary--",
+            MultipartLimits::default(),
+        );
+
+        let part = multipart.next_part().await.unwrap().unwrap();
+        assert_eq!(part.name(), "upload");
+        assert_eq!(part.filename(), Some("a;b.txt"));
+    }
+
+    #[tokio::test]
+    async fn rejects_malformed_dispositions() {
+        let mut multipart = multipart(
+            b"--boundary\r\nContent-Disposition: form-data; name=\"a\r\n\r\nvalue\r\n--boundary--",
+            MultipartLimits::default(),
+        );
+
+        assert!(matches!(
+            multipart.next_part().await,
+            Err(HttpError::BadRequest { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn rejects_parts_over_the_limit() {
+        let limits = MultipartLimits {
+            max_part_size: 64,
+            ..MultipartLimits::default()
+        };
+        let mut multipart = multipart(BODY, limits);
+
+        assert!(multipart.next_part().await.unwrap().is_some());
+        assert!(matches!(
+            multipart.next_part().await,
+            Err(HttpError::PayloadTooLarge { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn rejects_bodies_over_the_limit() {
+        let limits = MultipartLimits {
+            max_total_size: 32,
+            ..MultipartLimits::default()
+        };
+
+        assert!(matches!(
+            multipart(BODY, limits).next_part().await,
+            Err(HttpError::PayloadTooLarge { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn rejects_truncated_bodies() {
+        let mut multipart = multipart(
+            b"--boundary\r\nContent-Disposition: form-data; name=\"a\"\r\n\r\nvalue",
+            MultipartLimits::default(),
+        );
+
+        assert!(matches!(
+            multipart.next_part().await,
+            Err(HttpError::BadRequest { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn rejects_parts_without_a_name() {
+        let mut multipart = multipart(
+            b"--boundary\r\nContent-Disposition: form-data\r\n\r\nvalue\r\n--boundary--",
+            MultipartLimits::default(),
+        );
+
+        assert!(matches!(
+            multipart.next_part().await,
+            Err(HttpError::BadRequest { .. })
+        ));
+    }
+}
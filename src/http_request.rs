@@ -1,5 +1,16 @@
-use crate::{HttpBody, HttpCookie, HttpError, HttpHeader, HttpMethod};
-use std::fmt::Display;
+use crate::{
+    http_body::too_large, BodyReader, ContentType, CookieKeys, HttpBody, HttpCookie, HttpError,
+    HttpExtensions, HttpHeader, HttpHeaderMap, HttpLimits, HttpMethod, Multipart, MultipartLimits,
+    PrivateCookieJar, Session, SignedCookieJar, TypedHeader, UrlEncodedMap,
+};
+use percent_encoding::percent_decode_str;
+use serde::de::DeserializeOwned;
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    fmt::Display,
+    sync::{Arc, Mutex},
+};
 
 #[derive(Debug, Clone, Copy, Default)]
 pub enum HttpVersion {
@@ -8,11 +19,42 @@ pub enum HttpVersion {
     Http1_1,
 }
 
+impl HttpVersion {
+    /// Parses the `HTTP-version` of a request line, failing with [`HttpError::BadRequest`]
+    /// if it is malformed and with [`HttpError::HttpVersionNotSupported`] for any version
+    /// other than 1.0 and 1.1
+    fn parse(version: &str) -> Result<HttpVersion, HttpError> {
+        match version {
+            "HTTP/1.1" => return Ok(HttpVersion::Http1_1),
+            "HTTP/1.0" => return Ok(HttpVersion::Http1_0),
+            _ => {}
+        }
+
+        // `HTTP/` followed by a major and a minor digit, RFC 9112, section 2.3
+        let is_well_formed =
+            version
+                .strip_prefix("HTTP/")
+                .is_some_and(|number| match number.as_bytes() {
+                    [major, b'.', minor] => major.is_ascii_digit() && minor.is_ascii_digit(),
+                    _ => false,
+                });
+
+        match is_well_formed {
+            true => Err(HttpError::HttpVersionNotSupported {
+                message: format!("Unsupported HTTP Version `{version}`"),
+            }),
+            false => Err(HttpError::BadRequest {
+                message: "Invalid HTTP Version".to_string(),
+            }),
+        }
+    }
+}
+
 impl Display for HttpVersion {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let version = match self {
-            HttpVersion::Http1_0 => 1.0,
-            HttpVersion::Http1_1 => 1.1,
+            HttpVersion::Http1_0 => "1.0",
+            HttpVersion::Http1_1 => "1.1",
         };
 
         write!(f, "{version}",)
@@ -23,9 +65,20 @@ impl Display for HttpVersion {
 pub struct HttpRequest {
     pub version: HttpVersion,
     pub method: HttpMethod,
-    pub headers: Vec<HttpHeader>,
+    pub headers: HttpHeaderMap,
+    /// The percent-decoded path of the request target, without the query
     pub path: String,
+    /// The path as received, still percent-encoded, which routes are matched against
+    raw_path: String,
+    original_path: String,
+    query: UrlEncodedMap,
     pub body: Option<HttpBody>, // @todo
+    params: HashMap<String, String>,
+    state: Arc<HttpExtensions>,
+    extensions: HttpExtensions,
+    /// Filled in once a chunked body has been read to its end
+    trailers: Arc<Mutex<HttpHeaderMap>>,
+    max_body_size: usize,
 }
 
 impl HttpRequest {
@@ -46,7 +99,13 @@ impl HttpRequest {
             }),
         }?;
 
-        let mut request_line = request_line.split(" ");
+        if request_line.bytes().any(|byte| byte.is_ascii_control()) {
+            return Err(HttpError::BadRequest {
+                message: "Invalid character in Request Line".to_string(),
+            });
+        }
+
+        let mut request_line = request_line.split(' ');
 
         let method = match request_line.next() {
             Some(method_string) => HttpMethod::new(method_string),
@@ -55,94 +114,573 @@ impl HttpRequest {
             }),
         }?;
 
-        let path = match request_line.next() {
-            Some(path) => Ok(String::from(path)),
+        let target = match request_line.next() {
+            Some(target) => Ok(target),
             None => Err(HttpError::BadRequest {
                 message: "Invalid Path".to_string(),
             }),
         }?;
 
-        let version = match request_line.next() {
-            Some(version) => Ok(match &version[version.len() - 3..] {
-                "1.1" => HttpVersion::Http1_1,
-                "1.0" => HttpVersion::Http1_0,
-                _ => HttpVersion::default(),
+        let (raw_path, query) = target.split_once('?').unwrap_or((target, ""));
+
+        let path = match percent_decode_str(raw_path).decode_utf8() {
+            Ok(path) => Ok(path.into_owned()),
+            Err(_) => Err(HttpError::BadRequest {
+                message: "Invalid UTF-8 Sequence in path".to_string(),
             }),
-            None => Err(HttpError::BadRequest {
+        }?;
+
+        let version = match (request_line.next(), request_line.next()) {
+            (Some(version), None) => HttpVersion::parse(version),
+            _ => Err(HttpError::BadRequest {
                 message: "Invalid HTTP Version".to_string(),
             }),
         }?;
 
-        let headers = request_metadata.filter_map(HttpHeader::from).collect();
+        let headers = request_metadata
+            .map(HttpHeader::parse)
+            .collect::<Result<_, _>>()?;
 
         Ok(HttpRequest {
             version,
             method,
             headers,
+            original_path: path.clone(),
             path,
+            raw_path: raw_path.to_string(),
+            query: UrlEncodedMap::parse(query),
             body: None,
+            params: HashMap::new(),
+            state: Arc::default(),
+            extensions: HttpExtensions::new(),
+            trailers: Arc::default(),
+            max_body_size: HttpLimits::default().max_body_size,
         })
     }
 
-    pub fn get_content_length(&self) -> Option<usize> {
-        self.get_header("Content-Length").map_or(None, |header| {
-            header
-                .value
-                .parse()
-                .map_or(None, |content_length| Some(content_length))
+    /// The parameters of the query string, in order and including repeated names
+    pub fn query(&self) -> &UrlEncodedMap {
+        &self.query
+    }
+
+    /// Deserializes the query string into `T`, failing with [`HttpError::BadRequest`]
+    pub fn query_as<T: DeserializeOwned>(&self) -> Result<T, HttpError> {
+        self.query.deserialize()
+    }
+
+    /// Reads the whole body into memory, failing with [`HttpError::PayloadTooLarge`] once it
+    /// is larger than the maximum body size of the server.
+    ///
+    /// Use [`HttpRequest::body_reader`] to process a large body without buffering it.
+    pub async fn bytes(&self) -> Result<Vec<u8>, HttpError> {
+        // Fails before reading anything when the client announces a body that is too large
+        if self
+            .get_content_length()
+            .is_some_and(|length| length > self.max_body_size)
+        {
+            return Err(too_large(self.max_body_size));
+        }
+
+        match &self.body {
+            Some(body) => body.to_bytes(self.max_body_size).await,
+            None => Ok(vec![]),
+        }
+    }
+
+    /// Reads the body as it arrives from the client, see [`HttpBody::reader`]
+    pub fn body_reader(&self) -> BodyReader {
+        match &self.body {
+            Some(body) => body.reader(),
+            None => HttpBody::new(vec![]).reader(),
+        }
+    }
+
+    /// Deserializes the JSON body into `T`.
+    ///
+    /// Fails with [`HttpError::UnsupportedMediaType`] unless the `Content-Type` is
+    /// `application/json` or a `+json` type, and with [`HttpError::BadRequest`] if the body
+    /// is not valid JSON for `T`.
+    pub async fn json<T: DeserializeOwned>(&self) -> Result<T, HttpError> {
+        let mime_type = self.get_mime_type();
+
+        if mime_type != "application/json" && !mime_type.ends_with("+json") {
+            return Err(self.unsupported_media_type("a JSON"));
+        }
+
+        serde_json::from_slice(&self.bytes().await?).map_err(|error| HttpError::BadRequest {
+            message: format!("Invalid JSON body: {error}"),
         })
     }
 
+    /// Decodes an `application/x-www-form-urlencoded` body.
+    ///
+    /// Fails with [`HttpError::UnsupportedMediaType`] for any other `Content-Type`.
+    pub async fn form(&self) -> Result<UrlEncodedMap, HttpError> {
+        if self.get_mime_type() != "application/x-www-form-urlencoded" {
+            return Err(self.unsupported_media_type("a url-encoded form"));
+        }
+
+        match String::from_utf8(self.bytes().await?) {
+            Ok(body) => Ok(UrlEncodedMap::parse(&body)),
+            Err(_) => Err(HttpError::BadRequest {
+                message: "Invalid UTF-8 Sequence in form body".to_string(),
+            }),
+        }
+    }
+
+    /// Deserializes an `application/x-www-form-urlencoded` body into `T`
+    pub async fn form_as<T: DeserializeOwned>(&self) -> Result<T, HttpError> {
+        self.form().await?.deserialize()
+    }
+
+    /// Starts parsing a `multipart/form-data` body as it arrives, see [`Multipart::next_part`].
+    ///
+    /// Fails with [`HttpError::UnsupportedMediaType`] for any other `Content-Type` and with
+    /// [`HttpError::BadRequest`] if the `Content-Type` is malformed or has no boundary.
+    pub fn multipart(&self, limits: MultipartLimits) -> Result<Multipart<BodyReader>, HttpError> {
+        if self.get_mime_type() != "multipart/form-data" {
+            return Err(self.unsupported_media_type("a multipart form"));
+        }
+
+        let content_type = self.get_typed_header::<ContentType>()?;
+        let boundary = content_type
+            .as_ref()
+            .and_then(|content_type| content_type.parameter("boundary"))
+            .filter(|boundary| !boundary.is_empty())
+            .ok_or(HttpError::BadRequest {
+                message: "Multipart Content-Type without a boundary".to_string(),
+            })?;
+
+        Ok(Multipart::new(self.body_reader(), boundary, limits))
+    }
+
+    /// The lowercased media type of the `Content-Type`, without its parameters
+    fn get_mime_type(&self) -> String {
+        self.get_content_type()
+            .unwrap_or_default()
+            .split(';')
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .to_ascii_lowercase()
+    }
+
+    fn unsupported_media_type(&self, expected: &str) -> HttpError {
+        HttpError::UnsupportedMediaType {
+            message: format!(
+                "Expected {expected} body, found `{}`",
+                self.get_content_type().unwrap_or_default()
+            ),
+        }
+    }
+
+    pub fn get_content_length(&self) -> Option<usize> {
+        self.get_header("Content-Length")
+            .and_then(|value| value.parse().ok())
+    }
+
     pub fn get_content_type(&self) -> Option<String> {
-        self.get_header("Content-Type")
-            .map(|header| header.value.clone())
+        self.get_header("Content-Type").map(str::to_string)
     }
 
     pub fn set_body(&mut self, body: HttpBody) {
         self.body = Some(body);
     }
 
-    pub fn get_header(&self, name: &str) -> Option<&HttpHeader> {
-        self.headers.iter().find(|header| header.name == name)
+    /// The trailer fields sent after a `Transfer-Encoding: chunked` body, which are only
+    /// known once the body has been read
+    pub fn trailers(&self) -> HttpHeaderMap {
+        self.trailers
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone()
+    }
+
+    pub(crate) fn set_trailers(&mut self, trailers: Arc<Mutex<HttpHeaderMap>>) {
+        self.trailers = trailers;
+    }
+
+    /// The maximum size of the body read by [`HttpRequest::bytes`] and the methods parsing
+    /// the body
+    pub fn max_body_size(&self) -> usize {
+        self.max_body_size
+    }
+
+    pub(crate) fn set_max_body_size(&mut self, max_body_size: usize) {
+        self.max_body_size = max_body_size;
+    }
+
+    /// Values captured by the matched route pattern, keyed by parameter name
+    pub fn params(&self) -> &HashMap<String, String> {
+        &self.params
+    }
+
+    pub fn get_param(&self, name: &str) -> Option<&str> {
+        self.params.get(name).map(String::as_str)
+    }
+
+    pub(crate) fn extend_params(&mut self, params: HashMap<String, String>) {
+        self.params.extend(params);
+    }
+
+    /// The still percent-encoded path to route the request by, so that an encoded `/` is not
+    /// taken for a segment separator. A `path` rewritten by middleware is routed as if the
+    /// client had sent it.
+    pub(crate) fn routing_path(&self) -> Cow<'_, str> {
+        if percent_decode_str(&self.raw_path).decode_utf8_lossy() == self.path {
+            Cow::Borrowed(&self.raw_path)
+        } else {
+            Cow::Owned(self.path.replace('%', "%25"))
+        }
+    }
+
+    /// Replaces the path with the percent-encoded `raw_path`, e.g. the rest of the path seen
+    /// by a mounted router
+    pub(crate) fn set_raw_path(&mut self, raw_path: String) {
+        self.path = percent_decode_str(&raw_path)
+            .decode_utf8_lossy()
+            .into_owned();
+        self.raw_path = raw_path;
+    }
+
+    /// The path as received from the client, before any mounted router stripped its prefix
+    pub fn original_path(&self) -> &str {
+        &self.original_path
+    }
+
+    /// Borrows the application state of type `T` registered on the server
+    pub fn state<T: Send + Sync + 'static>(&self) -> Option<&T> {
+        self.state.get()
+    }
+
+    pub(crate) fn set_state(&mut self, state: Arc<HttpExtensions>) {
+        self.state = state;
+    }
+
+    /// Values attached to this request by middleware
+    pub fn extensions(&self) -> &HttpExtensions {
+        &self.extensions
+    }
+
+    pub fn extensions_mut(&mut self) -> &mut HttpExtensions {
+        &mut self.extensions
+    }
+
+    /// The session loaded by [`SessionMiddleware`](crate::SessionMiddleware), failing with
+    /// [`HttpError::InternalServerError`] if the middleware does not run for this route
+    pub fn session(&self) -> Result<&Session, HttpError> {
+        self.extensions.get().ok_or(HttpError::InternalServerError {
+            message: "No SessionMiddleware runs for this route".to_string(),
+        })
+    }
+
+    /// The value of the first header named `name`, compared case-insensitively
+    pub fn get_header(&self, name: &str) -> Option<&str> {
+        self.headers.get(name)
+    }
+
+    /// Parses a header into `T`, e.g. [`Accept`](crate::Accept), failing with
+    /// [`HttpError::BadRequest`] if it is malformed
+    pub fn get_typed_header<T: TypedHeader>(&self) -> Result<Option<T>, HttpError> {
+        self.headers.get_typed()
+    }
+
+    pub fn set_typed_header<T: TypedHeader>(&mut self, header: &T) -> Result<(), HttpError> {
+        self.headers.insert_typed(header)
     }
 
     pub fn get_cookies(&self) -> Vec<HttpCookie> {
         self.headers
             .iter()
-            .filter(|header| header.name == "Cookie")
-            .map(HttpCookie::from_header)
+            .filter(|header| header.name().eq_ignore_ascii_case("Cookie"))
+            .flat_map(HttpCookie::from_header)
             .collect()
     }
 
-    pub fn get_cookie(&self, name: String) -> Option<HttpCookie> {
+    pub fn get_cookie(&self, name: &str) -> Option<HttpCookie> {
         self.get_cookies()
-            .iter()
-            .find(|cookie| cookie.name == name)
-            .map(|cookie| cookie.clone())
+            .into_iter()
+            .find(|cookie| cookie.name() == name)
+    }
+
+    /// The cookies of the request whose signature is valid for the server's [`CookieKeys`]
+    pub fn signed_cookies(&self) -> Result<SignedCookieJar<'_>, HttpError> {
+        Ok(SignedCookieJar::new(
+            self.cookie_keys()?,
+            self.get_cookies(),
+        ))
+    }
+
+    /// The cookies of the request that can be decrypted with the server's [`CookieKeys`]
+    pub fn private_cookies(&self) -> Result<PrivateCookieJar<'_>, HttpError> {
+        Ok(PrivateCookieJar::new(
+            self.cookie_keys()?,
+            self.get_cookies(),
+        ))
+    }
+
+    fn cookie_keys(&self) -> Result<&CookieKeys, HttpError> {
+        self.state().ok_or(HttpError::InternalServerError {
+            message: "No CookieKeys registered on the server".to_string(),
+        })
     }
 }
 
 impl Display for HttpRequest {
     fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(
-            formatter,
-            "{} {} HTTP/{}\r\n",
-            self.method, self.path, self.version
-        )?;
+        write!(formatter, "{} {}", self.method, self.path)?;
 
-        for header in self.headers.clone() {
-            write!(formatter, "{header}\r\n")?;
+        if !self.query.as_str().is_empty() {
+            write!(formatter, "?{}", self.query.as_str())?;
         }
 
-        write!(formatter, "\r\n")?;
+        write!(formatter, " HTTP/{}\r\n", self.version)?;
+
+        write!(formatter, "{}\r\n", self.headers)?;
 
         if let Some(body) = &self.body {
-            match std::str::from_utf8(&body.as_bytes()) {
-                Ok(string) => write!(formatter, "{string}")?,
-                Err(_) => write!(formatter, "{:?}", self.get_content_type())?,
+            match body.as_bytes() {
+                Some(bytes) => match std::str::from_utf8(&bytes) {
+                    Ok(string) => write!(formatter, "{string}")?,
+                    Err(_) => write!(formatter, "{:?}", self.get_content_type())?,
+                },
+                None => write!(formatter, "<streaming body>")?,
             };
         }
 
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    fn request(content_type: &str, body: &str) -> HttpRequest {
+        let mut request = HttpRequest::new(
+            format!("POST /form HTTP/1.1\r\nHost: localhost\r\nContent-Type: {content_type}")
+                .into_bytes(),
+        )
+        .unwrap();
+        request.set_body(HttpBody::from(body));
+        request
+    }
+
+    #[tokio::test]
+    async fn decodes_url_encoded_forms() {
+        let request = request(
+            "application/x-www-form-urlencoded; charset=utf-8",
+            "name=J%C3%BCrgen+M&tag=a&tag=b&empty=",
+        );
+        let form = request.form().await.unwrap();
+
+        assert_eq!(form.get("name"), Some("Jürgen M"));
+        assert_eq!(form.get_all("tag"), vec!["a", "b"]);
+        assert_eq!(form.get("empty"), Some(""));
+        assert!(!form.contains_key("missing"));
+    }
+
+    #[tokio::test]
+    async fn deserializes_url_encoded_forms() {
+        #[derive(Deserialize)]
+        struct Login {
+            user: String,
+            remember: bool,
+        }
+
+        let login: Login = request(
+            "application/x-www-form-urlencoded",
+            "user=ada&remember=true",
+        )
+        .form_as()
+        .await
+        .unwrap();
+        assert_eq!(login.user, "ada");
+        assert!(login.remember);
+
+        let invalid = request(
+            "application/x-www-form-urlencoded",
+            "user=ada&remember=maybe",
+        )
+        .form_as::<Login>()
+        .await;
+        assert!(matches!(invalid, Err(HttpError::BadRequest { .. })));
+    }
+
+    #[tokio::test]
+    async fn rejects_forms_of_other_content_types() {
+        assert!(matches!(
+            request("application/json", "{}").form().await,
+            Err(HttpError::UnsupportedMediaType { .. })
+        ));
+        assert!(matches!(
+            request("text/plain", "").multipart(MultipartLimits::default()),
+            Err(HttpError::UnsupportedMediaType { .. })
+        ));
+        for content_type in [
+            "multipart/form-data",
+            "multipart/form-data; boundary=",
+            "multipart/form-data; boundary=\"\"",
+        ] {
+            assert!(
+                matches!(
+                    request(content_type, "").multipart(MultipartLimits::default()),
+                    Err(HttpError::BadRequest { .. })
+                ),
+                "{content_type}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn reads_multipart_bodies() {
+        let body = "--xyz\r\nContent-Disposition: form-data; name=\"a\"\r\n\r\n1\r\n--xyz--\r\n";
+        let mut multipart = request("multipart/form-data; boundary=\"xyz\"", body)
+            .multipart(MultipartLimits::default())
+            .unwrap();
+
+        let part = multipart.next_part().await.unwrap().unwrap();
+        assert_eq!((part.name(), part.text().unwrap()), ("a", "1"));
+        assert!(multipart.next_part().await.unwrap().is_none());
+    }
+
+    fn parse_version(version: &str) -> Result<HttpVersion, HttpError> {
+        HttpRequest::new(format!("GET /plain {version}\r\nHost: localhost").into_bytes())
+            .map(|request| request.version)
+    }
+
+    #[test]
+    fn parses_supported_versions() {
+        assert!(matches!(
+            parse_version("HTTP/1.1"),
+            Ok(HttpVersion::Http1_1)
+        ));
+        assert!(matches!(
+            parse_version("HTTP/1.0"),
+            Ok(HttpVersion::Http1_0)
+        ));
+        assert_eq!(HttpVersion::Http1_0.to_string(), "1.0");
+    }
+
+    #[test]
+    fn rejects_malformed_versions() {
+        for version in [
+            "X",
+            "HTTP/",
+            "HTTP/1",
+            "HTTP/1.",
+            "HTTP/1.1.1",
+            "http/1.1",
+            "HTTP/ü.1",
+        ] {
+            assert!(
+                matches!(parse_version(version), Err(HttpError::BadRequest { .. })),
+                "{version}"
+            );
+        }
+
+        assert!(matches!(
+            HttpRequest::new(b"GET /plain".to_vec()),
+            Err(HttpError::BadRequest { .. })
+        ));
+        assert!(matches!(
+            HttpRequest::new(b"GET /plain HTTP/1.1 extra".to_vec()),
+            Err(HttpError::BadRequest { .. })
+        ));
+    }
+
+    #[test]
+    fn rejects_unsupported_versions() {
+        for version in ["HTTP/2.0", "HTTP/0.9", "HTTP/1.2"] {
+            assert!(
+                matches!(
+                    parse_version(version),
+                    Err(HttpError::HttpVersionNotSupported { .. })
+                ),
+                "{version}"
+            );
+        }
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Item {
+        name: String,
+        count: u32,
+    }
+
+    #[tokio::test]
+    async fn decodes_json_bodies() {
+        let body = r#"{ "name": "pen", "count": 3 }"#;
+        let expected = Item {
+            name: "pen".to_string(),
+            count: 3,
+        };
+
+        for content_type in [
+            "application/json",
+            "application/json; charset=utf-8",
+            "Application/JSON",
+            "application/problem+json",
+        ] {
+            let item: Item = request(content_type, body).json().await.unwrap();
+            assert_eq!(item, expected, "{content_type}");
+        }
+    }
+
+    #[tokio::test]
+    async fn rejects_json_of_other_content_types() {
+        let mut untyped =
+            HttpRequest::new(b"POST /form HTTP/1.1\r\nHost: localhost".to_vec()).unwrap();
+        untyped.set_body(HttpBody::from("{}"));
+
+        assert!(matches!(
+            untyped.json::<serde_json::Value>().await,
+            Err(HttpError::UnsupportedMediaType { .. })
+        ));
+        assert!(matches!(
+            request("text/plain", "{}")
+                .json::<serde_json::Value>()
+                .await,
+            Err(HttpError::UnsupportedMediaType { .. })
+        ));
+        assert!(matches!(
+            request("application/jsonp", "{}")
+                .json::<serde_json::Value>()
+                .await,
+            Err(HttpError::UnsupportedMediaType { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn rejects_malformed_json() {
+        for body in [
+            "{",
+            r#"{ "name": "pen" }"#,
+            r#"{ "name": "pen", "count": -1 }"#,
+        ] {
+            assert!(
+                matches!(
+                    request("application/json", body).json::<Item>().await,
+                    Err(HttpError::BadRequest { .. })
+                ),
+                "{body}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn limits_the_size_of_json_bodies() {
+        let body = r#"{ "name": "pen", "count": 3 }"#;
+
+        let mut request = request("application/json", body);
+        request.set_max_body_size(body.len() - 1);
+        assert!(matches!(
+            request.json::<Item>().await,
+            Err(HttpError::PayloadTooLarge { .. })
+        ));
+
+        request.set_max_body_size(body.len());
+        assert!(request.json::<Item>().await.is_ok());
+    }
+}
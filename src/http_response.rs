@@ -1,35 +1,51 @@
-use crate::{HttpBody, HttpHeader, HttpVersion};
-use chrono;
+use crate::{
+    http_body::ByteStream, http_date::current_http_date, HttpBody, HttpCookie, HttpError,
+    HttpHeader, HttpHeaderMap, HttpVersion, Location, StatusCode, TypedHeader,
+};
+use serde::Serialize;
 use std::fmt::Display;
 
+/// Conversion of a handler's return value into the response sent to the client
+pub trait IntoResponse {
+    fn into_response(self) -> HttpResponse;
+}
+
+impl IntoResponse for HttpResponse {
+    fn into_response(self) -> HttpResponse {
+        self
+    }
+}
+
+impl<T: IntoResponse> IntoResponse for Result<T, HttpError> {
+    fn into_response(self) -> HttpResponse {
+        match self {
+            Ok(response) => response.into_response(),
+            Err(error) => error.as_response(),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct HttpResponse {
-    status: u16,
-    headers: Vec<HttpHeader>,
+    status: StatusCode,
+    headers: HttpHeaderMap,
     version: HttpVersion,
     body: Option<HttpBody>,
 }
 
 impl HttpResponse {
-    pub fn new(status: u16, body: Option<HttpBody>) -> HttpResponse {
-        let mut headers = vec![
-            HttpHeader {
-                name: "Server".to_string(),
-                value: "Hyperion".to_string(),
-            },
-            HttpHeader {
-                name: "Date".to_string(),
-                value: chrono::Utc::now().to_rfc3339(),
-            },
-        ];
-
-        headers.push(HttpHeader {
-            name: "Content-Length".to_string(),
-            value: match &body {
-                Some(body) => body.len().to_string(),
-                None => "0".to_string(),
-            },
-        });
+    pub fn new(status: StatusCode, body: Option<HttpBody>) -> HttpResponse {
+        let mut headers = HttpHeaderMap::new();
+
+        headers.append(HttpHeader::new_unchecked("Server", "Hyperion".to_string()));
+        headers.append(HttpHeader::new_unchecked("Date", current_http_date()));
+        // The length of a streaming body is unknown, the server frames it when writing it
+        if let Some(length) = body.as_ref().map_or(Some(0), HttpBody::len) {
+            headers.append(HttpHeader::new_unchecked(
+                "Content-Length",
+                length.to_string(),
+            ));
+        }
 
         HttpResponse {
             status,
@@ -39,60 +55,268 @@ impl HttpResponse {
         }
     }
 
-    fn get_status_text<'a>(status_code: u16) -> &'a str {
-        match status_code {
-            201 => "Created",
-            200 => "OK",
-            204 => "No Content",
-            205 => "Reset Content",
-            301 => "Moved Permanently",
-            304 => "Not Modified",
-            307 => "Temporary Redirect",
-            400 => "Bad Request",
-            401 => "Unauthorized",
-            403 => "Forbidden",
-            404 => "Not Found",
-            405 => "Method Not Allowed",
-            418 => "I'm a teapot",
-            429 => "Too Many Requests ",
-            501 => "Not Implemented",
-            502 => "Bad Gateway",
-            503 => "Service Unavailable",
-            500 | _ => "Internal Server Error",
-        }
+    /// Serializes `value` as the body with `Content-Type: application/json`, answering with
+    /// a 500 instead if `value` cannot be serialized
+    pub fn json<T: Serialize + ?Sized>(status: StatusCode, value: &T) -> HttpResponse {
+        let Ok(bytes) = serde_json::to_vec(value) else {
+            return HttpResponse::new(StatusCode::INTERNAL_SERVER_ERROR, None);
+        };
+
+        let mut response = HttpResponse::new(status, Some(HttpBody::new(bytes)));
+        response.set_header(HttpHeader::new_unchecked(
+            "Content-Type",
+            "application/json".to_string(),
+        ));
+        response
+    }
+
+    pub fn builder() -> HttpResponseBuilder {
+        HttpResponseBuilder::new()
+    }
+
+    /// A `200 OK` response with `body`
+    pub fn ok(body: impl Into<HttpBody>) -> HttpResponse {
+        HttpResponse::new(StatusCode::OK, Some(body.into()))
+    }
+
+    pub fn not_found() -> HttpResponse {
+        HttpResponse::new(StatusCode::NOT_FOUND, None)
     }
 
+    /// A `307 Temporary Redirect` to `location`, which repeats the request with the same
+    /// method and body
+    pub fn redirect(location: &str) -> Result<HttpResponse, HttpError> {
+        HttpResponse::redirect_with(StatusCode::TEMPORARY_REDIRECT, location)
+    }
+
+    /// A `308 Permanent Redirect` to `location`
+    pub fn permanent_redirect(location: &str) -> Result<HttpResponse, HttpError> {
+        HttpResponse::redirect_with(StatusCode::PERMANENT_REDIRECT, location)
+    }
+
+    /// A `303 See Other` to `location`, which is fetched with GET, e.g. after a form was posted
+    pub fn see_other(location: &str) -> Result<HttpResponse, HttpError> {
+        HttpResponse::redirect_with(StatusCode::SEE_OTHER, location)
+    }
+
+    fn redirect_with(status: StatusCode, location: &str) -> Result<HttpResponse, HttpError> {
+        HttpResponse::builder()
+            .status(status)
+            .typed_header(&Location(location.to_string()))
+            .build()
+    }
+
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+
+    pub fn set_status(&mut self, status: StatusCode) {
+        self.status = status;
+    }
+
+    pub fn body(&self) -> Option<&HttpBody> {
+        self.body.as_ref()
+    }
+
+    /// The head of the response followed by its body. A streaming body is left out, as it
+    /// is written chunk by chunk from its stream.
     pub fn as_bytes(&self) -> Vec<u8> {
         let mut response = Vec::from(format!("{self}").as_bytes());
 
-        if let Some(body) = &self.body {
-            response.extend(body.as_bytes());
+        if let Some(bytes) = self.body.as_ref().and_then(HttpBody::as_bytes) {
+            response.extend(bytes);
         }
 
         response
     }
 
+    /// Takes the stream of a streaming body, see [`HttpBody::stream`]
+    pub(crate) fn take_stream(&self) -> Option<ByteStream> {
+        self.body.as_ref().and_then(HttpBody::take_stream)
+    }
+
+    /// Drops the body while keeping the headers, including `Content-Length`, as a response to
+    /// a HEAD request requires
+    pub(crate) fn strip_body(&mut self) {
+        self.body = None;
+    }
+
+    /// Adds `header`, keeping any header with the same name, e.g. for `Set-Cookie`
     pub fn add_header(&mut self, header: HttpHeader) {
-        self.headers.push(header);
+        self.headers.append(header);
+    }
+
+    /// Sets `header`, replacing any header with the same name
+    pub fn set_header(&mut self, header: HttpHeader) {
+        self.headers.insert(header);
+    }
+
+    /// The value of the first header named `name`, compared case-insensitively
+    pub fn get_header(&self, name: &str) -> Option<&str> {
+        self.headers.get(name)
+    }
+
+    /// Parses a header set on this response into `T`
+    pub fn get_typed_header<T: TypedHeader>(&self) -> Result<Option<T>, HttpError> {
+        self.headers.get_typed()
+    }
+
+    /// Sets a header from `T`, e.g. [`ETag`](crate::ETag), replacing any header with the
+    /// same name
+    pub fn set_typed_header<T: TypedHeader>(&mut self, header: &T) -> Result<(), HttpError> {
+        self.headers.insert_typed(header)
+    }
+
+    pub fn headers(&self) -> &HttpHeaderMap {
+        &self.headers
+    }
+
+    pub fn headers_mut(&mut self) -> &mut HttpHeaderMap {
+        &mut self.headers
+    }
+
+    /// Adds a `Set-Cookie` header for `cookie`
+    pub fn set_cookie(&mut self, cookie: &HttpCookie) {
+        self.add_header(cookie.to_header());
+    }
+
+    /// Adds a `Set-Cookie` header making the client delete `cookie`, whose domain and path
+    /// must match the ones it was set with
+    pub fn remove_cookie(&mut self, cookie: &HttpCookie) {
+        self.set_cookie(&cookie.to_removal());
     }
 }
 
 impl Display for HttpResponse {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(
-            f,
-            "HTTP/{} {} {}\r\n",
-            self.version,
-            self.status,
-            HttpResponse::get_status_text(self.status)
-        )?;
+        write!(f, "HTTP/{} {}\r\n", self.version, self.status)?;
+
+        write!(f, "{}\r\n", self.headers)?;
+
+        Ok(())
+    }
+}
+
+/// Builds an [`HttpResponse`], e.g.
+/// `HttpResponse::builder().status(StatusCode::CREATED).header("Location", "/users/1").build()`.
+///
+/// Invalid headers are reported once the response is built.
+#[derive(Debug, Default)]
+pub struct HttpResponseBuilder {
+    status: StatusCode,
+    headers: Vec<HttpHeader>,
+    error: Option<HttpError>,
+}
+
+impl HttpResponseBuilder {
+    pub fn new() -> HttpResponseBuilder {
+        HttpResponseBuilder::default()
+    }
+
+    pub fn status(mut self, status: StatusCode) -> HttpResponseBuilder {
+        self.status = status;
+        self
+    }
+
+    /// Adds a header, keeping any header with the same name
+    pub fn header(mut self, name: &str, value: &str) -> HttpResponseBuilder {
+        match HttpHeader::new(name, value) {
+            Ok(header) => self.headers.push(header),
+            Err(error) => {
+                self.error.get_or_insert(error);
+            }
+        }
+
+        self
+    }
 
+    pub fn typed_header<T: TypedHeader>(self, header: &T) -> HttpResponseBuilder {
+        self.header(T::NAME, &header.encode())
+    }
+
+    /// Builds the response with `body`, failing with the first error of an invalid header
+    pub fn body(self, body: impl Into<HttpBody>) -> Result<HttpResponse, HttpError> {
+        self.finish(Some(body.into()))
+    }
+
+    /// Builds the response without a body, failing with the first error of an invalid header
+    pub fn build(self) -> Result<HttpResponse, HttpError> {
+        self.finish(None)
+    }
+
+    fn finish(self, body: Option<HttpBody>) -> Result<HttpResponse, HttpError> {
+        if let Some(error) = self.error {
+            return Err(error);
+        }
+
+        let mut response = HttpResponse::new(self.status, body);
+
+        // Headers set on the builder replace the defaults, e.g. `Server` or `Content-Length`,
+        // while several headers of the same name set on the builder are all kept
         for header in &self.headers {
-            write!(f, "{header}\r\n")?;
+            response.headers.remove(header.name());
         }
 
-        write!(f, "\r\n")?;
+        response.headers.extend(self.headers);
+        Ok(response)
+    }
+}
 
-        Ok(())
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builder_headers_replace_defaults() {
+        let response = HttpResponse::builder()
+            .header("Content-Length", "3")
+            .header("Server", "mine")
+            .header("Set-Cookie", "a=1")
+            .header("Set-Cookie", "b=2")
+            .body("abc")
+            .unwrap();
+
+        let headers = response.headers();
+        assert_eq!(headers.get_all("Content-Length").collect::<Vec<_>>(), ["3"]);
+        assert_eq!(headers.get_all("Server").collect::<Vec<_>>(), ["mine"]);
+        assert_eq!(headers.get_all("Set-Cookie").count(), 2);
+        assert_eq!(headers.get_all("Date").count(), 1);
+    }
+
+    #[test]
+    fn builder_reports_invalid_headers() {
+        let response = HttpResponse::builder()
+            .header("X-Injected", "a\r\nSet-Cookie: b=2")
+            .build();
+
+        assert!(matches!(
+            response,
+            Err(HttpError::InternalServerError { .. })
+        ));
+    }
+
+    #[test]
+    fn serializes_json_bodies() {
+        let response = HttpResponse::json(StatusCode::CREATED, &serde_json::json!({ "id": 7 }));
+
+        assert_eq!(response.status(), StatusCode::CREATED);
+        assert_eq!(
+            response.get_header("Content-Type"),
+            Some("application/json")
+        );
+        assert_eq!(response.get_header("Content-Length"), Some("8"));
+        assert_eq!(
+            response.body().and_then(HttpBody::as_bytes).unwrap(),
+            br#"{"id":7}"#
+        );
+    }
+
+    #[test]
+    fn answers_unserializable_json_with_500() {
+        let value = std::collections::HashMap::from([((1, 2), "tuple keys")]);
+        let response = HttpResponse::json(StatusCode::OK, &value);
+
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert!(response.get_header("Content-Type").is_none());
     }
 }
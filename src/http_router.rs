@@ -1,69 +1,921 @@
-use crate::{HttpRequest, HttpResponse};
+use crate::{
+    http_header::is_token,
+    http_middleware::{layer, Middleware},
+    HttpError, HttpHeader, HttpMethod, HttpRequest, HttpResponse, IntoResponse, StatusCode,
+};
 use futures::{future::BoxFuture, Future};
-use std::collections::HashMap;
-use tokio::sync::RwLock;
+use percent_encoding::percent_decode_str;
+use std::{collections::HashMap, sync::Arc};
 
-struct RequestHandler {
-    func: Box<dyn Fn(HttpRequest) -> BoxFuture<'static, HttpResponse> + Send + Sync + 'static>,
+type BoxedHandler =
+    Arc<dyn Fn(HttpRequest) -> BoxFuture<'static, HttpResponse> + Send + Sync + 'static>;
+
+#[derive(Clone)]
+pub(crate) struct RequestHandler {
+    func: BoxedHandler,
 }
 
 impl RequestHandler {
-    pub fn new<P>(func: fn(HttpRequest) -> P) -> RequestHandler
+    pub fn new<F, P>(func: F) -> RequestHandler
     where
-        P: Future<Output = HttpResponse> + Send + 'static,
+        F: Fn(HttpRequest) -> P + Send + Sync + 'static,
+        P: Future + Send + 'static,
+        P::Output: IntoResponse,
     {
         RequestHandler {
-            func: Box::new(move |request| Box::pin(func(request))),
+            func: Arc::new(move |request| {
+                let response = func(request);
+                Box::pin(async move { response.await.into_response() })
+            }),
         }
     }
 
+    pub fn from_boxed(func: BoxedHandler) -> RequestHandler {
+        RequestHandler { func }
+    }
+
     pub async fn call(&self, request: HttpRequest) -> HttpResponse {
         (self.func)(request).await
     }
 }
 
+/// A single segment of a route pattern
+enum Segment<'a> {
+    /// Matches the segment literally, e.g. `users`
+    Static(&'a str),
+    /// Matches any non-empty segment and captures it, e.g. `:id`
+    Param(&'a str),
+    /// Matches the rest of the path (possibly empty) and captures it, e.g. `*rest`
+    Glob(&'a str),
+}
+
+impl<'a> Segment<'a> {
+    fn parse(segment: &'a str) -> Segment<'a> {
+        if let Some(name) = segment.strip_prefix(':') {
+            Segment::Param(name)
+        } else if let Some(name) = segment.strip_prefix('*') {
+            Segment::Glob(name)
+        } else {
+            Segment::Static(segment)
+        }
+    }
+}
+
+/// A value captured while matching a path, as the index of the segment it starts at
+enum Capture {
+    /// A single segment, captured by a parameter
+    Segment(usize),
+    /// The segment and every one after it, captured by a glob or a mounted router
+    Rest(usize),
+}
+
+/// Handlers registered for a single route, keyed by method
+#[derive(Default)]
+struct MethodRouter {
+    handlers: Vec<(HttpMethod, RequestHandler)>,
+    fallback: Option<RequestHandler>,
+}
+
+impl MethodRouter {
+    fn insert(&mut self, method: Option<HttpMethod>, handler: RequestHandler) {
+        let Some(method) = method else {
+            self.fallback = Some(handler);
+            return;
+        };
+
+        match self
+            .handlers
+            .iter_mut()
+            .find(|(existing, _)| *existing == method)
+        {
+            Some((_, existing)) => *existing = handler,
+            None => self.handlers.push((method, handler)),
+        }
+    }
+
+    /// The handler for `method`, serving HEAD requests with the GET handler unless there is a
+    /// HEAD handler, before falling back to the handler for every method
+    fn get(&self, method: &HttpMethod) -> Option<&RequestHandler> {
+        self.find(method)
+            .or_else(|| match method {
+                HttpMethod::HEAD => self.find(&HttpMethod::GET),
+                _ => None,
+            })
+            .or(self.fallback.as_ref())
+    }
+
+    fn is_empty(&self) -> bool {
+        self.handlers.is_empty() && self.fallback.is_none()
+    }
+
+    fn find(&self, method: &HttpMethod) -> Option<&RequestHandler> {
+        self.handlers
+            .iter()
+            .find(|(existing, _)| existing == method)
+            .map(|(_, handler)| handler)
+    }
+
+    /// The registered methods, plus HEAD when GET is registered and OPTIONS, which are
+    /// answered automatically
+    fn allowed_methods(&self) -> Vec<HttpMethod> {
+        let mut methods: Vec<_> = self
+            .handlers
+            .iter()
+            .map(|(method, _)| method.clone())
+            .collect();
+
+        if methods.contains(&HttpMethod::GET) && !methods.contains(&HttpMethod::HEAD) {
+            methods.push(HttpMethod::HEAD);
+        }
+
+        if !methods.contains(&HttpMethod::OPTIONS) {
+            methods.push(HttpMethod::OPTIONS);
+        }
+
+        methods
+    }
+}
+
+#[derive(Default)]
+struct Route {
+    /// Names of the captured segments, in the order they appear in the pattern
+    param_names: Vec<String>,
+    methods: MethodRouter,
+    middlewares: Vec<Arc<dyn Middleware>>,
+    /// A router handling every request under this route, registered through [`HttpRouter::mount`]
+    mount: Option<Arc<HttpRouter>>,
+}
+
+impl Route {
+    /// Whether the route only carries middleware, registered before or without a handler, in
+    /// which case it does not match
+    fn is_empty(&self) -> bool {
+        self.methods.is_empty() && self.mount.is_none()
+    }
+}
+
+#[derive(Default)]
+struct RouteNode {
+    statics: HashMap<String, RouteNode>,
+    param: Option<Box<RouteNode>>,
+    glob: Option<Route>,
+    route: Option<Route>,
+}
+
+impl RouteNode {
+    /// Returns the route for `pattern`, creating it and any missing nodes on the way
+    fn route_mut(&mut self, pattern: &str) -> &mut Route {
+        let mut node = self;
+        let mut param_names = vec![];
+        let mut segments = split_path(pattern).peekable();
+
+        while let Some(segment) = segments.next() {
+            node = match Segment::parse(segment) {
+                Segment::Static(segment) => node.statics.entry(segment.to_string()).or_default(),
+                Segment::Param(name) => {
+                    param_names.push(name.to_string());
+                    node.param.get_or_insert_with(Default::default)
+                }
+                Segment::Glob(name) => {
+                    if segments.peek().is_some() {
+                        panic!("Glob segment must be the last segment in route `{pattern}`");
+                    }
+
+                    param_names.push(name.to_string());
+                    let route = node.glob.get_or_insert_with(Default::default);
+                    route.param_names = param_names;
+                    return route;
+                }
+            };
+        }
+
+        let route = node.route.get_or_insert_with(Default::default);
+        route.param_names = param_names;
+        route
+    }
+
+    /// Finds the route for the decoded `segments` from `index` on, pushing what is captured
+    /// onto `captures`.
+    ///
+    /// Static segments are tried first, then parameters, then globs, backtracking when a
+    /// branch does not lead to a route.
+    fn find(
+        &self,
+        segments: &[String],
+        index: usize,
+        captures: &mut Vec<Capture>,
+    ) -> Option<&Route> {
+        match segments.get(index) {
+            None => {
+                if let Some(route) = self.route.as_ref().filter(|route| !route.is_empty()) {
+                    return Some(route);
+                }
+            }
+            Some(segment) => {
+                if let Some(route) = self
+                    .statics
+                    .get(segment)
+                    .and_then(|child| child.find(segments, index + 1, captures))
+                {
+                    return Some(route);
+                }
+
+                if let Some(child) = self.param.as_ref().filter(|_| !segment.is_empty()) {
+                    captures.push(Capture::Segment(index));
+
+                    if let Some(route) = child.find(segments, index + 1, captures) {
+                        return Some(route);
+                    }
+
+                    captures.pop();
+                }
+            }
+        }
+
+        self.glob
+            .as_ref()
+            .filter(|route| !route.is_empty())
+            .inspect(|_| captures.push(Capture::Rest(index)))
+    }
+
+    /// Every route below this node, mounted routers excluded
+    fn routes(&self) -> Vec<&Route> {
+        let children = self.statics.values().chain(self.param.as_deref());
+
+        self.route
+            .iter()
+            .chain(&self.glob)
+            .filter(|route| !route.is_empty())
+            .chain(children.flat_map(RouteNode::routes))
+            .collect()
+    }
+}
+
+fn split_path(path: &str) -> std::str::Split<'_, char> {
+    path.strip_prefix('/').unwrap_or(path).split('/')
+}
+
+fn decode_segment(segment: &str) -> String {
+    percent_decode_str(segment).decode_utf8_lossy().into_owned()
+}
+
+/// Joins decoded segments for a glob, keeping `/` and `%` inside a segment encoded so that
+/// each `/` of the value separates two segments of the path, e.g. `..%2F..` stays a single
+/// segment rather than becoming `../..`
+fn join_segments(segments: &[String]) -> String {
+    segments
+        .iter()
+        .map(|segment| segment.replace('%', "%25").replace('/', "%2F"))
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
 pub struct HttpRouter {
-    handlers: HashMap<String, RequestHandler>,
+    root: RouteNode,
+    middlewares: Vec<Arc<dyn Middleware>>,
+}
+
+impl Default for HttpRouter {
+    fn default() -> Self {
+        HttpRouter::new()
+    }
 }
 
 impl HttpRouter {
     pub fn new() -> HttpRouter {
         HttpRouter {
-            handlers: HashMap::new(),
+            root: RouteNode::default(),
+            middlewares: vec![],
         }
     }
 
-    pub fn add_handler<P>(&mut self, path: &str, func: fn(HttpRequest) -> P)
+    /// Registers a handler for a route pattern and method, or for every method when `method`
+    /// is `None`.
+    ///
+    /// Patterns are made of `/` separated segments, which are either matched literally,
+    /// captured as a named parameter (`/users/:id`) or, as the last segment only, captured
+    /// as a glob over the remainder of the path (`/files/*rest`). Captured values are
+    /// available through [`HttpRequest::params`].
+    ///
+    /// When several patterns match a path, static segments take priority over parameters,
+    /// which take priority over globs. Registering the same pattern and method twice replaces
+    /// the previous handler.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a glob segment is not the last segment of the pattern, if the pattern ends
+    /// with a glob where a router is mounted, see [`HttpRouter::mount`], or if `method` is an
+    /// extension method whose name is not a token, as it would end up in the `Allow` header.
+    pub fn route<F, P>(&mut self, method: Option<HttpMethod>, path: &str, func: F)
     where
-        P: Future<Output = HttpResponse> + Send + 'static,
+        F: Fn(HttpRequest) -> P + Send + Sync + 'static,
+        P: Future + Send + 'static,
+        P::Output: IntoResponse,
     {
-        self.handlers
-            .insert(path.to_string(), RequestHandler::new(func));
+        if let Some(HttpMethod::Extension(name)) = &method {
+            if !is_token(name) {
+                panic!("Invalid method `{}` in route `{path}`", name.escape_debug());
+            }
+        }
+
+        let route = self.root.route_mut(path);
+
+        if route.mount.is_some() {
+            panic!("Route `{path}` conflicts with a router mounted on its prefix");
+        }
+
+        route.methods.insert(method, RequestHandler::new(func));
+    }
+
+    pub fn get<F, P>(&mut self, path: &str, func: F)
+    where
+        F: Fn(HttpRequest) -> P + Send + Sync + 'static,
+        P: Future + Send + 'static,
+        P::Output: IntoResponse,
+    {
+        self.route(Some(HttpMethod::GET), path, func);
+    }
+
+    pub fn post<F, P>(&mut self, path: &str, func: F)
+    where
+        F: Fn(HttpRequest) -> P + Send + Sync + 'static,
+        P: Future + Send + 'static,
+        P::Output: IntoResponse,
+    {
+        self.route(Some(HttpMethod::POST), path, func);
     }
 
-    fn match_path(&self, path: String) -> Option<&RequestHandler> {
-        if let Some(handler) = self.handlers.get(&path) {
-            return Some(handler);
+    pub fn put<F, P>(&mut self, path: &str, func: F)
+    where
+        F: Fn(HttpRequest) -> P + Send + Sync + 'static,
+        P: Future + Send + 'static,
+        P::Output: IntoResponse,
+    {
+        self.route(Some(HttpMethod::PUT), path, func);
+    }
+
+    pub fn delete<F, P>(&mut self, path: &str, func: F)
+    where
+        F: Fn(HttpRequest) -> P + Send + Sync + 'static,
+        P: Future + Send + 'static,
+        P::Output: IntoResponse,
+    {
+        self.route(Some(HttpMethod::DELETE), path, func);
+    }
+
+    pub fn patch<F, P>(&mut self, path: &str, func: F)
+    where
+        F: Fn(HttpRequest) -> P + Send + Sync + 'static,
+        P: Future + Send + 'static,
+        P::Output: IntoResponse,
+    {
+        self.route(Some(HttpMethod::PATCH), path, func);
+    }
+
+    /// Registers a handler for every method that has no handler of its own on `path`
+    pub fn any<F, P>(&mut self, path: &str, func: F)
+    where
+        F: Fn(HttpRequest) -> P + Send + Sync + 'static,
+        P: Future + Send + 'static,
+        P::Output: IntoResponse,
+    {
+        self.route(None, path, func);
+    }
+
+    /// Runs `middleware` around every route of this router.
+    ///
+    /// See [`Middleware`] for the order in which middleware runs.
+    pub fn middleware<M: Middleware>(&mut self, middleware: M) {
+        self.middlewares.push(Arc::new(middleware));
+    }
+
+    /// Runs `middleware` around the handlers of the route pattern `path`, for every method.
+    /// The pattern only matches requests once a handler is registered for it.
+    pub fn route_middleware<M: Middleware>(&mut self, path: &str, middleware: M) {
+        self.root
+            .route_mut(path)
+            .middlewares
+            .push(Arc::new(middleware));
+    }
+
+    /// Hands every request under `prefix` to `router`, which sees the path with the prefix
+    /// stripped. The full path stays available through [`HttpRequest::original_path`].
+    ///
+    /// The prefix may contain parameters, whose values are added to the mounted router's
+    /// own. Routes registered on this router take priority over the mounted router.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `prefix` contains a glob segment, if a router is already mounted on it, or
+    /// if a glob route such as `prefix/*rest` is registered, as both would handle the same
+    /// requests.
+    pub fn mount(&mut self, prefix: &str, router: HttpRouter) {
+        if split_path(prefix).any(|segment| segment.starts_with('*')) {
+            panic!("Mount prefix `{prefix}` must not contain a glob segment");
         }
 
-        // @todo Implement Pattern Matching for Wildcard Paths
+        let route = self
+            .root
+            .route_mut(&format!("{}/*", prefix.trim_end_matches('/')));
 
-        None
+        if route.mount.is_some() {
+            panic!("A router is already mounted on `{prefix}`");
+        }
+
+        if !route.methods.is_empty() {
+            panic!("Mount prefix `{prefix}` conflicts with a glob route on the same prefix");
+        }
+
+        // The remainder of the path is captured as the last value, without a name
+        route.param_names.pop();
+        route.mount = Some(Arc::new(router));
     }
 
-    pub async fn process_request(&self, request: HttpRequest) -> HttpResponse {
-        let path = request.path.clone();
+    /// Matches the percent-encoded `path`, returning the route, its decoded parameters and,
+    /// for mounted routers, the still encoded remainder of the path.
+    ///
+    /// The path is split before its segments are decoded, so an encoded `/` is part of a
+    /// segment rather than a separator.
+    fn match_path(&self, path: &str) -> Option<(&Route, HashMap<String, String>, Option<String>)> {
+        let raw_segments: Vec<_> = split_path(path).collect();
+        let segments: Vec<_> = raw_segments
+            .iter()
+            .map(|segment| decode_segment(segment))
+            .collect();
+        let mut captures = vec![];
 
-        if let Some(handler) = self.match_path(path) {
-            handler.call(request).await
-        } else {
-            HttpResponse::new(404, None)
+        let route = self.root.find(&segments, 0, &mut captures)?;
+
+        let rest = match (&route.mount, captures.last()) {
+            (Some(_), Some(&Capture::Rest(index))) => {
+                captures.pop();
+                Some(raw_segments[index..].join("/"))
+            }
+            _ => None,
+        };
+
+        let values = captures.iter().map(|capture| match *capture {
+            Capture::Segment(index) => segments[index].clone(),
+            Capture::Rest(index) => join_segments(&segments[index..]),
+        });
+        let params = route.param_names.iter().cloned().zip(values).collect();
+
+        Some((route, params, rest))
+    }
+
+    /// The methods allowed on any route of this router or of the routers mounted on it
+    fn allowed_methods(&self) -> Vec<HttpMethod> {
+        let mut methods = vec![];
+
+        for route in self.root.routes() {
+            let route_methods = match &route.mount {
+                Some(router) => router.allowed_methods(),
+                None => route.methods.allowed_methods(),
+            };
+
+            for method in route_methods {
+                if !methods.contains(&method) {
+                    methods.push(method);
+                }
+            }
+        }
+
+        methods
+    }
+
+    pub async fn process_request(&self, mut request: HttpRequest) -> HttpResponse {
+        let path = request.routing_path().into_owned();
+
+        // `OPTIONS *` asks about the server as a whole rather than a single resource
+        if request.method == HttpMethod::OPTIONS && path == "*" {
+            let handler = HttpRouter::options_handler(self.allowed_methods());
+            return layer(handler, &self.middlewares).call(request).await;
         }
+
+        let Some((route, params, rest)) = self.match_path(&path) else {
+            let handler = HttpRouter::response_handler(HttpResponse::not_found());
+            return layer(handler, &self.middlewares).call(request).await;
+        };
+
+        // Responses for methods without a handler run inside the middleware as well, so that
+        // e.g. CORS or logging middleware sees them
+        let handler = match (&route.mount, rest) {
+            (Some(router), Some(rest)) => HttpRouter::mounted_handler(router.clone(), rest),
+            _ => match route.methods.get(&request.method) {
+                Some(handler) => handler.clone(),
+                None if request.method == HttpMethod::OPTIONS => {
+                    HttpRouter::options_handler(route.methods.allowed_methods())
+                }
+                // A method the server knows nothing about is not implemented rather than not
+                // allowed, as RFC 9110 recommends
+                None if matches!(request.method, HttpMethod::Extension(_)) => {
+                    HttpRouter::response_handler(
+                        HttpError::NotImplemented {
+                            message: format!("Method `{}` is not implemented", request.method),
+                        }
+                        .as_response(),
+                    )
+                }
+                None => HttpRouter::response_handler(HttpRouter::allow_response(
+                    StatusCode::METHOD_NOT_ALLOWED,
+                    &route.methods.allowed_methods(),
+                )),
+            },
+        };
+
+        let handler = layer(handler, &route.middlewares);
+        let handler = layer(handler, &self.middlewares);
+
+        request.extend_params(params);
+        handler.call(request).await
+    }
+
+    fn mounted_handler(router: Arc<HttpRouter>, rest: String) -> RequestHandler {
+        RequestHandler::new(move |mut request: HttpRequest| {
+            let router = router.clone();
+            request.set_raw_path(format!("/{rest}"));
+
+            async move { router.process_request(request).await }
+        })
+    }
+
+    /// Answers OPTIONS requests for routes without an OPTIONS handler. It runs inside the
+    /// middleware so that e.g. CORS preflight requests can be answered by middleware.
+    fn options_handler(allowed_methods: Vec<HttpMethod>) -> RequestHandler {
+        HttpRouter::response_handler(HttpRouter::allow_response(StatusCode::OK, &allowed_methods))
+    }
+
+    /// A handler answering with `response`, for responses the router makes itself
+    fn response_handler(response: HttpResponse) -> RequestHandler {
+        RequestHandler::new(move |_| {
+            let response = response.clone();
+            async move { response }
+        })
+    }
+
+    fn allow_response(status: StatusCode, allowed_methods: &[HttpMethod]) -> HttpResponse {
+        let allow: Vec<_> = allowed_methods.iter().map(HttpMethod::as_str).collect();
+
+        let mut response = HttpResponse::new(status, None);
+        response.set_header(HttpHeader::new_unchecked("Allow", allow.join(", ")));
+        response
     }
 }
 
-lazy_static::lazy_static! {
-    pub static ref ROUTER: RwLock<HttpRouter> = RwLock::new(
-        HttpRouter::new()
-    );
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{HttpBody, Next};
+
+    fn request(method: &str, target: &str) -> HttpRequest {
+        HttpRequest::new(format!("{method} {target} HTTP/1.1\r\nHost: localhost").into_bytes())
+            .unwrap()
+    }
+
+    /// Answers with the name of the handler followed by the parameters, sorted by name
+    fn named(name: &'static str) -> impl Fn(HttpRequest) -> BoxFuture<'static, HttpResponse> {
+        move |request| {
+            let mut params: Vec<_> = request
+                .params()
+                .iter()
+                .map(|(name, value)| format!("{name}={value}"))
+                .collect();
+            params.sort();
+
+            let body = [name.to_string()]
+                .into_iter()
+                .chain(params)
+                .collect::<Vec<_>>();
+            Box::pin(async move { HttpResponse::ok(body.join(" ")) })
+        }
+    }
+
+    async fn send(router: &HttpRouter, method: &str, target: &str) -> (u16, String) {
+        let response = router.process_request(request(method, target)).await;
+        let body = response
+            .body()
+            .and_then(HttpBody::as_bytes)
+            .unwrap_or_default();
+
+        (response.status().as_u16(), String::from_utf8(body).unwrap())
+    }
+
+    async fn allow(router: &HttpRouter, method: &str, target: &str) -> Option<String> {
+        let response = router.process_request(request(method, target)).await;
+        response.get_header("Allow").map(str::to_string)
+    }
+
+    #[tokio::test]
+    async fn static_segments_take_priority_over_parameters_and_globs() {
+        let mut router = HttpRouter::new();
+        router.get("/users/*rest", named("glob"));
+        router.get("/users/:id", named("param"));
+        router.get("/users/me", named("static"));
+
+        assert_eq!(send(&router, "GET", "/users/me").await.1, "static");
+        assert_eq!(send(&router, "GET", "/users/42").await.1, "param id=42");
+        assert_eq!(
+            send(&router, "GET", "/users/42/posts").await.1,
+            "glob rest=42/posts"
+        );
+        assert_eq!(send(&router, "GET", "/users").await.1, "glob rest=");
+    }
+
+    #[tokio::test]
+    async fn backtracks_out_of_static_branches_without_a_route() {
+        let mut router = HttpRouter::new();
+        router.get("/a/b/d", named("static"));
+        router.get("/a/:x/c", named("param"));
+        router.get("/*rest", named("glob"));
+
+        assert_eq!(send(&router, "GET", "/a/b/d").await.1, "static");
+        assert_eq!(send(&router, "GET", "/a/b/c").await.1, "param x=b");
+        assert_eq!(send(&router, "GET", "/a/b/e").await.1, "glob rest=a/b/e");
+    }
+
+    #[tokio::test]
+    async fn parameters_do_not_match_empty_segments() {
+        let mut router = HttpRouter::new();
+        router.get("/users/:id", named("param"));
+
+        assert_eq!(send(&router, "GET", "/users/").await.0, 404);
+        assert_eq!(send(&router, "GET", "/users/1/2").await.0, 404);
+    }
+
+    #[test]
+    #[should_panic(expected = "Glob segment must be the last segment")]
+    fn rejects_globs_before_the_last_segment() {
+        HttpRouter::new().get("/files/*rest/edit", named("glob"));
+    }
+
+    #[tokio::test]
+    async fn answers_unknown_methods_with_405_and_allow() {
+        let mut router = HttpRouter::new();
+        router.get("/items", named("get"));
+        router.post("/items", named("post"));
+
+        assert_eq!(send(&router, "DELETE", "/items").await.0, 405);
+        assert_eq!(
+            allow(&router, "DELETE", "/items").await.as_deref(),
+            Some("GET, POST, HEAD, OPTIONS")
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid method")]
+    fn rejects_extension_methods_that_are_not_tokens() {
+        HttpRouter::new().route(
+            Some(HttpMethod::Extension("X\r\nInjected: 1".to_string())),
+            "/items",
+            named("injected"),
+        );
+    }
+
+    #[tokio::test]
+    async fn lists_extension_methods_in_allow() {
+        let mut router = HttpRouter::new();
+        router.route(
+            Some(HttpMethod::Extension("PURGE".to_string())),
+            "/items",
+            named("purge"),
+        );
+
+        assert_eq!(send(&router, "PURGE", "/items").await.1, "purge");
+        assert_eq!(
+            allow(&router, "DELETE", "/items").await.as_deref(),
+            Some("PURGE, OPTIONS")
+        );
+    }
+
+    #[tokio::test]
+    async fn answers_extension_methods_with_501() {
+        let mut router = HttpRouter::new();
+        router.get("/items", named("get"));
+
+        assert_eq!(send(&router, "PURGE", "/items").await.0, 501);
+        assert_eq!(send(&router, "PURGE", "/missing").await.0, 404);
+
+        // Method names are case-sensitive, so `get` is not GET
+        assert_eq!(send(&router, "get", "/items").await.0, 501);
+    }
+
+    #[tokio::test]
+    async fn routes_every_standard_method() {
+        let mut router = HttpRouter::new();
+        router.put("/items", named("put"));
+        router.patch("/items", named("patch"));
+        router.delete("/items", named("delete"));
+
+        router.route(Some(HttpMethod::OPTIONS), "/items", named("options"));
+        router.route(Some(HttpMethod::CONNECT), "/items", named("connect"));
+        router.route(Some(HttpMethod::TRACE), "/items", named("trace"));
+
+        for method in ["PUT", "PATCH", "DELETE", "OPTIONS", "CONNECT", "TRACE"] {
+            assert_eq!(
+                send(&router, method, "/items").await,
+                (200, method.to_lowercase())
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn fallback_handlers_answer_every_method() {
+        let mut router = HttpRouter::new();
+        router.get("/items", named("get"));
+        router.any("/items", named("any"));
+
+        assert_eq!(send(&router, "GET", "/items").await.1, "get");
+        assert_eq!(send(&router, "DELETE", "/items").await.1, "any");
+        assert_eq!(send(&router, "PURGE", "/items").await.1, "any");
+    }
+
+    #[tokio::test]
+    async fn answers_head_with_the_get_handler() {
+        let mut router = HttpRouter::new();
+        router.get("/items", named("get"));
+
+        assert_eq!(
+            send(&router, "HEAD", "/items").await,
+            (200, "get".to_string())
+        );
+
+        router.route(Some(HttpMethod::HEAD), "/items", named("head"));
+        assert_eq!(send(&router, "HEAD", "/items").await.1, "head");
+    }
+
+    #[tokio::test]
+    async fn answers_options_with_the_allowed_methods() {
+        let mut router = HttpRouter::new();
+        router.get("/items", named("get"));
+        router.put("/items/:id", named("put"));
+
+        assert_eq!(send(&router, "OPTIONS", "/items").await.0, 200);
+        assert_eq!(
+            allow(&router, "OPTIONS", "/items").await.as_deref(),
+            Some("GET, HEAD, OPTIONS")
+        );
+
+        let mut server_methods = allow(&router, "OPTIONS", "*").await.unwrap();
+        server_methods = server_methods.replace(' ', "");
+        let mut server_methods: Vec<_> = server_methods.split(',').collect();
+        server_methods.sort();
+        assert_eq!(server_methods, ["GET", "HEAD", "OPTIONS", "PUT"]);
+    }
+
+    #[tokio::test]
+    async fn mounted_routers_see_the_rest_of_the_path() {
+        let mut users = HttpRouter::new();
+        users.get("/", named("index"));
+        users.get("/:id", named("show"));
+
+        let mut router = HttpRouter::new();
+        router.get("/api/:version/users/me", named("me"));
+        router.mount("/api/:version/users", users);
+
+        assert_eq!(
+            send(&router, "GET", "/api/v1/users").await.1,
+            "index version=v1"
+        );
+        assert_eq!(
+            send(&router, "GET", "/api/v1/users/42").await.1,
+            "show id=42 version=v1"
+        );
+        assert_eq!(
+            send(&router, "GET", "/api/v1/users/me").await.1,
+            "me version=v1"
+        );
+        assert_eq!(send(&router, "GET", "/api/v1/users/1/2").await.0, 404);
+    }
+
+    #[tokio::test]
+    async fn encoded_slashes_do_not_separate_segments() {
+        let mut router = HttpRouter::new();
+        router.get("/users/:id", named("param"));
+        router.get("/files/*rest", named("glob"));
+
+        assert_eq!(send(&router, "GET", "/users/a%2Fb").await.1, "param id=a/b");
+        assert_eq!(
+            send(&router, "GET", "/users/caf%C3%A9").await.1,
+            "param id=café"
+        );
+        assert_eq!(
+            send(&router, "GET", "/files/..%2F..%2Fetc/passwd").await.1,
+            "glob rest=..%2F..%2Fetc/passwd"
+        );
+        assert_eq!(
+            send(&router, "GET", "/files/100%25/a%20b").await.1,
+            "glob rest=100%25/a b"
+        );
+    }
+
+    #[tokio::test]
+    async fn mounted_routers_receive_the_encoded_rest_of_the_path() {
+        let mut files = HttpRouter::new();
+        files.get("/:name", |request: HttpRequest| async move {
+            HttpResponse::ok(format!(
+                "{} {}",
+                request.path,
+                request.get_param("name").unwrap()
+            ))
+        });
+
+        let mut router = HttpRouter::new();
+        router.mount("/files", files);
+
+        assert_eq!(send(&router, "GET", "/files/a%2Fb").await.1, "/a/b a/b");
+    }
+
+    #[tokio::test]
+    async fn routes_paths_rewritten_by_middleware() {
+        let mut router = HttpRouter::new();
+        router.get("/new/:name", named("new"));
+
+        let mut request = request("GET", "/old/100%25");
+        request.path = "/new/100%".to_string();
+
+        let response = router.process_request(request).await;
+        let body = response
+            .body()
+            .and_then(HttpBody::as_bytes)
+            .unwrap_or_default();
+        assert_eq!(String::from_utf8(body).unwrap(), "new name=100%");
+    }
+
+    #[test]
+    #[should_panic(expected = "conflicts with a glob route")]
+    fn rejects_mounting_over_a_glob_route() {
+        let mut router = HttpRouter::new();
+        router.get("/api/*rest", named("glob"));
+        router.mount("/api", HttpRouter::new());
+    }
+
+    #[test]
+    #[should_panic(expected = "conflicts with a router mounted")]
+    fn rejects_glob_routes_over_a_mounted_router() {
+        let mut router = HttpRouter::new();
+        router.mount("/api/", HttpRouter::new());
+        router.get("/api/*rest", named("glob"));
+    }
+
+    #[test]
+    #[should_panic(expected = "already mounted")]
+    fn rejects_mounting_twice_on_a_prefix() {
+        let mut router = HttpRouter::new();
+        router.mount("/api", HttpRouter::new());
+        router.mount("/api", HttpRouter::new());
+    }
+
+    /// Middleware adding a header named `name` to every response
+    fn tag(name: &'static str) -> impl Fn(HttpRequest, Next) -> BoxFuture<'static, HttpResponse> {
+        move |request, next| {
+            Box::pin(async move {
+                let mut response = next.run(request).await;
+                response.set_header(HttpHeader::new(name, "1").unwrap());
+                response
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn middleware_sees_responses_made_by_the_router() {
+        let mut router = HttpRouter::new();
+        router.get("/items", named("get"));
+        router.middleware(tag("X-Router"));
+        router.route_middleware("/items", tag("X-Route"));
+
+        for (method, target, status) in [
+            ("GET", "/items", 200),
+            ("DELETE", "/items", 405),
+            ("PURGE", "/items", 501),
+            ("OPTIONS", "/items", 200),
+        ] {
+            let response = router.process_request(request(method, target)).await;
+            assert_eq!(response.status().as_u16(), status);
+            assert!(response.get_header("X-Router").is_some(), "{method}");
+            assert!(response.get_header("X-Route").is_some(), "{method}");
+        }
+
+        let response = router.process_request(request("GET", "/missing")).await;
+        assert_eq!(response.status().as_u16(), 404);
+        assert!(response.get_header("X-Router").is_some());
+    }
+
+    #[tokio::test]
+    async fn patterns_with_only_middleware_do_not_match() {
+        let mut router = HttpRouter::new();
+        router.route_middleware("/middleware-only", tag("X-Route"));
+        router.get("/:name", named("param"));
+
+        assert_eq!(
+            send(&router, "GET", "/middleware-only").await.1,
+            "param name=middleware-only"
+        );
+
+        router.route_middleware("/other/path", tag("X-Route"));
+        assert_eq!(send(&router, "GET", "/other/path").await.0, 404);
+        assert_eq!(
+            allow(&router, "OPTIONS", "*").await.as_deref(),
+            Some("GET, HEAD, OPTIONS")
+        );
+    }
 }
@@ -1,38 +1,203 @@
-use crate::{HttpBody, HttpError, HttpRequest, HttpResponse, ROUTER};
-use futures::Future;
-use std::{io::ErrorKind, process::exit};
+use crate::{
+    http_body::too_large,
+    http_body_reader::{IncomingBody, SharedReader},
+    http_chunked::{encode_chunk, read_line, LAST_CHUNK},
+    http_middleware::layer,
+    http_router::RequestHandler,
+    CookieKeys, HttpBody, HttpError, HttpExtensions, HttpHeader, HttpMethod, HttpRequest,
+    HttpResponse, HttpRouter, HttpVersion, IntoResponse, Middleware,
+};
+use futures::{Future, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::{io::ErrorKind, net::SocketAddr, process::exit, sync::Arc};
 use tokio::{
-    io::{AsyncReadExt, AsyncWriteExt, Interest, Result as IoResult},
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader, Result as IoResult},
     net::{
-        tcp::{ReadHalf, WriteHalf},
-        TcpListener, TcpStream, ToSocketAddrs,
+        tcp::{OwnedReadHalf, OwnedWriteHalf},
+        TcpListener, ToSocketAddrs,
     },
+    sync::Mutex,
 };
 
-const READ_REQUEST_BUFFER_SIZE: usize = 0xff;
+/// Room left in the request line for the method and version, next to the request target
+const REQUEST_LINE_OVERHEAD: usize = 32;
 
-pub struct HttpServer {
-    listener: TcpListener,
+/// Size limits enforced while reading requests, past which reading is aborted
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HttpLimits {
+    /// Maximum length of the request target, past which a 414 is sent
+    pub max_request_target: usize,
+    /// Maximum size of the headers, or of the trailers of a chunked body, past which a 431 is
+    /// sent
+    pub max_header_size: usize,
+    /// Maximum number of headers, past which a 431 is sent
+    pub max_headers: usize,
+    /// Maximum size of a body, past which a 413 is sent
+    pub max_body_size: usize,
 }
 
-impl HttpServer {
-    pub async fn add_handler<P>(&self, path: &str, func: fn(HttpRequest) -> P)
+impl Default for HttpLimits {
+    fn default() -> Self {
+        HttpLimits {
+            max_request_target: 8 * 1024,
+            max_header_size: 64 * 1024,
+            max_headers: 100,
+            max_body_size: 16 * 1024 * 1024,
+        }
+    }
+}
+
+/// Collects the routes and state of an [`HttpServer`] before it starts listening
+#[derive(Default)]
+pub struct HttpServerBuilder {
+    router: HttpRouter,
+    state: HttpExtensions,
+    middlewares: Vec<Arc<dyn Middleware>>,
+    limits: HttpLimits,
+}
+
+impl HttpServerBuilder {
+    pub fn new() -> HttpServerBuilder {
+        HttpServerBuilder::default()
+    }
+
+    /// Replaces the routes registered so far with `router`
+    pub fn router(mut self, router: HttpRouter) -> HttpServerBuilder {
+        self.router = router;
+        self
+    }
+
+    pub fn route<F, P>(
+        mut self,
+        method: Option<HttpMethod>,
+        path: &str,
+        func: F,
+    ) -> HttpServerBuilder
+    where
+        F: Fn(HttpRequest) -> P + Send + Sync + 'static,
+        P: Future + Send + 'static,
+        P::Output: IntoResponse,
+    {
+        self.router.route(method, path, func);
+        self
+    }
+
+    /// Hands every request under `prefix` to `router`, see [`HttpRouter::mount`]
+    pub fn mount(mut self, prefix: &str, router: HttpRouter) -> HttpServerBuilder {
+        self.router.mount(prefix, router);
+        self
+    }
+
+    pub fn get<F, P>(self, path: &str, func: F) -> HttpServerBuilder
+    where
+        F: Fn(HttpRequest) -> P + Send + Sync + 'static,
+        P: Future + Send + 'static,
+        P::Output: IntoResponse,
+    {
+        self.route(Some(HttpMethod::GET), path, func)
+    }
+
+    pub fn post<F, P>(self, path: &str, func: F) -> HttpServerBuilder
+    where
+        F: Fn(HttpRequest) -> P + Send + Sync + 'static,
+        P: Future + Send + 'static,
+        P::Output: IntoResponse,
+    {
+        self.route(Some(HttpMethod::POST), path, func)
+    }
+
+    pub fn put<F, P>(self, path: &str, func: F) -> HttpServerBuilder
+    where
+        F: Fn(HttpRequest) -> P + Send + Sync + 'static,
+        P: Future + Send + 'static,
+        P::Output: IntoResponse,
+    {
+        self.route(Some(HttpMethod::PUT), path, func)
+    }
+
+    pub fn delete<F, P>(self, path: &str, func: F) -> HttpServerBuilder
+    where
+        F: Fn(HttpRequest) -> P + Send + Sync + 'static,
+        P: Future + Send + 'static,
+        P::Output: IntoResponse,
+    {
+        self.route(Some(HttpMethod::DELETE), path, func)
+    }
+
+    pub fn patch<F, P>(self, path: &str, func: F) -> HttpServerBuilder
+    where
+        F: Fn(HttpRequest) -> P + Send + Sync + 'static,
+        P: Future + Send + 'static,
+        P::Output: IntoResponse,
+    {
+        self.route(Some(HttpMethod::PATCH), path, func)
+    }
+
+    pub fn any<F, P>(self, path: &str, func: F) -> HttpServerBuilder
     where
-        P: Future<Output = HttpResponse> + Send + 'static,
+        F: Fn(HttpRequest) -> P + Send + Sync + 'static,
+        P: Future + Send + 'static,
+        P::Output: IntoResponse,
     {
-        ROUTER.write().await.add_handler(path, func);
+        self.route(None, path, func)
+    }
+
+    /// Runs `middleware` around every request, including requests that match no route.
+    ///
+    /// See [`Middleware`] for the order in which middleware runs.
+    pub fn middleware<M: Middleware>(mut self, middleware: M) -> HttpServerBuilder {
+        self.middlewares.push(Arc::new(middleware));
+        self
+    }
+
+    /// Shares `state` with every handler, which can borrow it through [`HttpRequest::state`].
+    ///
+    /// State is keyed by type, so registering a second value of the same type replaces the
+    /// first one.
+    pub fn with_state<T: Send + Sync + 'static>(mut self, state: T) -> HttpServerBuilder {
+        self.state.insert(state);
+        self
+    }
+
+    /// Registers the keys used by [`HttpRequest::signed_cookies`] and
+    /// [`HttpRequest::private_cookies`]
+    pub fn cookie_keys(self, keys: CookieKeys) -> HttpServerBuilder {
+        self.with_state(keys)
     }
 
-    pub async fn new(addr: impl ToSocketAddrs) -> IoResult<HttpServer> {
+    /// Replaces the size limits of requests, e.g. with the ones of a
+    /// [`HyperionConfig`](crate::HyperionConfig)
+    pub fn limits(mut self, limits: HttpLimits) -> HttpServerBuilder {
+        self.limits = limits;
+        self
+    }
+
+    /// The maximum size of a request body, 16 MiB by default, see [`HttpLimits`]
+    pub fn max_body_size(mut self, max_body_size: usize) -> HttpServerBuilder {
+        self.limits.max_body_size = max_body_size;
+        self
+    }
+
+    pub async fn bind(self, addr: impl ToSocketAddrs) -> IoResult<HttpServer> {
+        let router = Arc::new(self.router);
+        let handler = RequestHandler::new(move |request| {
+            let router = router.clone();
+            async move { router.process_request(request).await }
+        });
+
         Ok(HttpServer {
             listener: TcpListener::bind(addr).await?,
+            handler: layer(handler, &self.middlewares),
+            state: Arc::new(self.state),
+            limits: self.limits,
         })
     }
 
-    pub async fn init() -> HttpServer {
+    pub async fn init(self) -> HttpServer {
         const PORT: u16 = 8080;
 
-        match HttpServer::new(("127.0.0.1", PORT)).await {
+        match self.bind(("127.0.0.1", PORT)).await {
             Ok(server) => {
                 println!("Server running on http://localhost:{PORT}");
                 server
@@ -46,10 +211,28 @@ impl HttpServer {
             }
         }
     }
+}
+
+pub struct HttpServer {
+    listener: TcpListener,
+    /// The router wrapped in the global middleware
+    handler: RequestHandler,
+    state: Arc<HttpExtensions>,
+    limits: HttpLimits,
+}
+
+impl HttpServer {
+    pub fn builder() -> HttpServerBuilder {
+        HttpServerBuilder::new()
+    }
+
+    pub fn local_addr(&self) -> IoResult<SocketAddr> {
+        self.listener.local_addr()
+    }
 
     pub async fn listen(&self) {
         loop {
-            let (mut stream, _) = match self.listener.accept().await {
+            let (stream, _) = match self.listener.accept().await {
                 Ok(peer) => peer,
                 Err(error) => {
                     println!("Failed to connect due to an error: {error}");
@@ -57,108 +240,832 @@ impl HttpServer {
                 }
             };
 
+            let handler = self.handler.clone();
+            let state = self.state.clone();
+            let limits = self.limits;
+
             tokio::spawn(async move {
-                match HttpServer::handle(&mut stream).await {
+                let (reader, mut writer) = stream.into_split();
+
+                match HttpServer::handle(reader, &mut writer, handler, state, limits).await {
                     Ok(response) => response,
-                    Err(error) => HttpServer::handle_error(&mut stream, error).await,
+                    Err(error) => HttpServer::handle_error(&mut writer, error).await,
                 }
             });
         }
     }
 
-    async fn handle_error(client: &mut TcpStream, error: HttpError) {
+    async fn handle_error(client: &mut OwnedWriteHalf, error: HttpError) {
         let response = error.as_response();
         let response_bytes = response.as_bytes();
 
-        client.write(&response_bytes).await.unwrap_or_else(|error| {
+        if let Err(error) = client.write_all(&response_bytes).await {
             println!("Failed to Write response due to error: {error} \n{response}");
-            0
-        });
+        }
     }
 
-    async fn handle(client: &mut TcpStream) -> Result<(), HttpError> {
-        let (mut stream_reader, mut stream_writer) = client.split();
+    async fn handle(
+        stream_reader: OwnedReadHalf,
+        stream_writer: &mut OwnedWriteHalf,
+        handler: RequestHandler,
+        state: Arc<HttpExtensions>,
+        limits: HttpLimits,
+    ) -> Result<(), HttpError> {
+        // Shared with the body of each request, which reads from the connection lazily
+        let stream_reader: SharedReader = Arc::new(Mutex::new(BufReader::new(stream_reader)));
 
-        while stream_reader.ready(Interest::READABLE).await?.is_readable() {
-            let request = HttpServer::read_request(&mut stream_reader).await?;
-            let router = ROUTER.read().await;
+        // Nothing left to read means the client closed the connection
+        while !stream_reader.lock().await.fill_buf().await?.is_empty() {
+            let (mut request, body) = HttpServer::read_request(&stream_reader, &limits).await?;
+            request.set_state(state.clone());
+            request.set_max_body_size(limits.max_body_size);
 
-            let response = router.process_request(request.clone()).await;
+            let response = handler.call(request.clone()).await;
+            let head = request.method == HttpMethod::HEAD;
 
-            HttpServer::respond(&mut stream_writer, response).await?;
+            let mut reusable =
+                HttpServer::respond(stream_writer, response, request.version, head).await?;
+
+            // The next request starts after the end of this body, which the handler might not
+            // have read. Closing the connection with the body unread could also make the
+            // client drop the response.
+            if let Some(body) = body {
+                reusable &= HttpServer::skip_body(&body, limits.max_body_size).await;
+            }
 
             let keep_alive = request
                 .get_header("Connection")
-                .map_or(false, |header| header.value == "keep-alive");
+                .is_some_and(|value| value.eq_ignore_ascii_case("keep-alive"));
 
-            if !keep_alive {
+            if !reusable || !keep_alive {
                 break;
             }
         }
 
-        Ok(client.shutdown().await?)
+        Ok(stream_writer.shutdown().await?)
     }
 
-    async fn read_request(stream_reader: &mut ReadHalf<'_>) -> Result<HttpRequest, HttpError> {
-        let request_bytes = HttpServer::read_request_metadata(stream_reader).await?;
+    /// Reads the rest of a body left unread by the handler, giving up on a body longer than
+    /// `max_size`. Returns whether the whole body was read.
+    async fn skip_body(body: &Mutex<IncomingBody>, max_size: usize) -> bool {
+        let mut body = body.lock().await;
+        let mut skipped = 0;
 
-        let mut request = HttpRequest::new(request_bytes)?;
+        if body
+            .remaining_length()
+            .is_some_and(|length| length > max_size)
+        {
+            return false;
+        }
 
-        if let Some(content_length) = request.get_content_length() {
-            // Reading Body
-            let body = HttpServer::read_request_body(stream_reader, content_length).await?;
-            request.set_body(body);
+        while skipped <= max_size {
+            match body.next_chunk().await {
+                Ok(Some(chunk)) => skipped += chunk.len(),
+                Ok(None) => break,
+                Err(_) => return false,
+            }
         }
-        Ok(request)
+
+        body.is_complete()
     }
 
-    async fn respond(stream_writer: &mut WriteHalf<'_>, response: HttpResponse) -> IoResult<usize> {
-        Ok(stream_writer.write(&response.as_bytes()).await?)
+    /// Reads the request line and headers, leaving the body to be read by the handler
+    async fn read_request(
+        stream_reader: &SharedReader,
+        limits: &HttpLimits,
+    ) -> Result<(HttpRequest, Option<Arc<Mutex<IncomingBody>>>), HttpError> {
+        let request_bytes =
+            HttpServer::read_request_metadata(&mut *stream_reader.lock().await, limits).await?;
+
+        let mut request = HttpRequest::new(request_bytes)?;
+
+        let transfer_encoding: Vec<_> = request
+            .headers
+            .get_all("Transfer-Encoding")
+            .flat_map(|value| value.split(','))
+            .map(str::trim)
+            .filter(|coding| !coding.is_empty())
+            .collect();
+
+        let content_length = HttpServer::content_length(&request)?;
+
+        let body = if transfer_encoding.is_empty() {
+            match content_length {
+                // Refused before the handler runs, rather than once the body has been read
+                Some(content_length) if content_length > limits.max_body_size => {
+                    return Err(too_large(limits.max_body_size));
+                }
+                Some(content_length) => {
+                    IncomingBody::with_length(stream_reader.clone(), content_length, limits)
+                }
+                None => return Ok((request, None)),
+            }
+        } else {
+            // A request with both headers is a request smuggling attempt, as an intermediary
+            // might have used the other header to find where the request ends
+            if content_length.is_some() {
+                return Err(HttpError::BadRequest {
+                    message: "Request with both Content-Length and Transfer-Encoding".to_string(),
+                });
+            }
+
+            // Without chunked as the final coding there is no way to tell where the body ends
+            if !transfer_encoding
+                .last()
+                .is_some_and(|coding| coding.eq_ignore_ascii_case("chunked"))
+            {
+                return Err(HttpError::BadRequest {
+                    message: "Transfer-Encoding must end with chunked".to_string(),
+                });
+            }
+
+            if transfer_encoding.len() > 1 {
+                return Err(HttpError::NotImplemented {
+                    message: format!(
+                        "Unsupported Transfer-Encoding `{}`",
+                        transfer_encoding.join(", ")
+                    ),
+                });
+            }
+
+            let trailers = Arc::default();
+            request.set_trailers(Arc::clone(&trailers));
+            IncomingBody::chunked(stream_reader.clone(), trailers, limits)
+        };
+
+        let body = Arc::new(Mutex::new(body));
+        request.set_body(HttpBody::stream(IncomingBody::into_stream(body.clone())));
+
+        Ok((request, Some(body)))
     }
 
-    async fn read_request_metadata(stream_reader: &mut ReadHalf<'_>) -> IoResult<Vec<u8>> {
-        let mut read_buffer = [0; READ_REQUEST_BUFFER_SIZE];
+    /// The `Content-Length` of `request`, failing with [`HttpError::BadRequest`] if it is not
+    /// a number or is sent several times with different values
+    fn content_length(request: &HttpRequest) -> Result<Option<usize>, HttpError> {
+        let mut content_length = None;
 
-        let mut result_buffer = vec![];
+        for value in request
+            .headers
+            .get_all("Content-Length")
+            .flat_map(|value| value.split(','))
+        {
+            let value = value.trim();
 
-        loop {
-            let read_bytes_count = stream_reader.peek(&mut read_buffer).await?; // Do not remove from TCP Buffer
-
-            // Actually Remove from TCP buffer now
-            if let Some(eof_index) = HttpServer::find_eof(&read_buffer) {
-                stream_reader.read(&mut read_buffer[..eof_index]).await?;
-                result_buffer.extend(&read_buffer[..eof_index]);
-                stream_reader.read(&mut [0; 4]).await?; // Clear EOF
-                return Ok(result_buffer);
-            } else {
-                stream_reader.read(&mut read_buffer).await?;
-                result_buffer.extend(&read_buffer[..read_bytes_count]);
+            let length = value
+                .bytes()
+                .all(|byte| byte.is_ascii_digit())
+                .then(|| value.parse::<usize>().ok())
+                .flatten()
+                .ok_or(HttpError::BadRequest {
+                    message: format!("Invalid Content-Length `{value}`"),
+                })?;
+
+            if content_length.is_some_and(|content_length| content_length != length) {
+                return Err(HttpError::BadRequest {
+                    message: "Conflicting Content-Length headers".to_string(),
+                });
             }
+
+            content_length = Some(length);
         }
+
+        Ok(content_length)
     }
 
-    fn find_eof(buffer: &[u8; READ_REQUEST_BUFFER_SIZE]) -> Option<usize> {
-        buffer
-            .windows(4)
-            .position(|window| window == &[13, 10, 13, 10])
+    /// Writes `response`, leaving out the body in answer to a HEAD request, and returns
+    /// whether the connection can be reused afterwards
+    async fn respond(
+        stream_writer: &mut OwnedWriteHalf,
+        mut response: HttpResponse,
+        version: HttpVersion,
+        head: bool,
+    ) -> IoResult<bool> {
+        let Some(mut stream) = response.take_stream() else {
+            if head {
+                response.strip_body();
+            }
+
+            stream_writer.write_all(&response.as_bytes()).await?;
+            return Ok(true);
+        };
+
+        // HTTP/1.0 has no chunked encoding, so the end of the body is marked by closing the
+        // connection instead
+        let chunked = matches!(version, HttpVersion::Http1_1);
+
+        if chunked {
+            response.set_header(HttpHeader::new_unchecked(
+                "Transfer-Encoding",
+                "chunked".to_string(),
+            ));
+        }
+
+        stream_writer.write_all(&response.as_bytes()).await?;
+
+        // A HEAD response announces the framing the GET response would use, without the body,
+        // so the stream is dropped unread
+        if head {
+            return Ok(true);
+        }
+
+        // Each chunk is written before the next one is pulled, so a slow client slows the
+        // stream down rather than having its chunks pile up in memory
+        while let Some(chunk) = stream.next().await {
+            let chunk = match chunk {
+                Ok(chunk) => chunk,
+                Err(error) => {
+                    println!("Failed to stream response body due to error: {error}");
+                    return Ok(false);
+                }
+            };
+
+            if chunked {
+                stream_writer.write_all(&encode_chunk(&chunk)).await?;
+            } else {
+                stream_writer.write_all(&chunk).await?;
+            }
+        }
+
+        if chunked {
+            stream_writer.write_all(LAST_CHUNK).await?;
+        }
+
+        Ok(chunked)
     }
 
-    async fn read_request_body(
-        stream_reader: &mut ReadHalf<'_>,
-        content_length: usize,
-    ) -> IoResult<HttpBody> {
-        let mut body_bytes = vec![0; content_length];
-        let mut read_bytes_total = 0;
+    /// Reads the request line and headers, without the empty line ending them
+    async fn read_request_metadata(
+        stream_reader: &mut BufReader<OwnedReadHalf>,
+        limits: &HttpLimits,
+    ) -> Result<Vec<u8>, HttpError> {
+        let uri_too_long = || HttpError::UriTooLong {
+            message: format!(
+                "Request target longer than {} bytes",
+                limits.max_request_target
+            ),
+        };
+        let headers_too_large = || HttpError::RequestHeaderFieldsTooLarge {
+            message: format!("Headers larger than {} bytes", limits.max_header_size),
+        };
+
+        let request_line_limit = limits.max_request_target + REQUEST_LINE_OVERHEAD;
+
+        let mut metadata = loop {
+            let line = read_line(stream_reader, request_line_limit, uri_too_long).await?;
+
+            // Empty lines before the request line are ignored, as RFC 9112 recommends
+            if !line.is_empty() && line != b"\r" {
+                break line;
+            }
+        };
+
+        let target = metadata.split(|byte| *byte == b' ').nth(1);
+
+        if target.is_some_and(|target| target.len() > limits.max_request_target) {
+            return Err(uri_too_long());
+        }
+
+        metadata.push(b'\n');
+
+        let mut header_size = 0;
+        let mut header_count = 0;
+
         loop {
-            let read_bytes_count = stream_reader
-                .read(&mut body_bytes[read_bytes_total..])
-                .await?;
+            // Leaves room for the line break of the empty line ending the headers
+            let line_limit = limits.max_header_size - header_size + 2;
+            let line = read_line(stream_reader, line_limit, headers_too_large).await?;
 
-            read_bytes_total += read_bytes_count;
+            if line.is_empty() || line == b"\r" {
+                if line.is_empty() || !metadata.ends_with(b"\r\n") {
+                    return Err(HttpError::BadRequest {
+                        message: "Bare LF in request headers".to_string(),
+                    });
+                }
 
-            if read_bytes_total >= content_length {
-                return Ok(HttpBody::new(body_bytes));
+                // The last line break belongs to the empty line
+                metadata.truncate(metadata.len() - 2);
+                return Ok(metadata);
             }
+
+            header_size += line.len() + 1;
+            header_count += 1;
+
+            if header_size > limits.max_header_size {
+                return Err(headers_too_large());
+            }
+
+            if header_count > limits.max_headers {
+                return Err(HttpError::RequestHeaderFieldsTooLarge {
+                    message: format!("More than {} headers", limits.max_headers),
+                });
+            }
+
+            metadata.extend(line);
+            metadata.push(b'\n');
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::{io::AsyncReadExt, net::TcpStream};
+
+    /// Starts a server answering `POST /echo` with the body of the request and its trailers
+    /// as `Trailer-*` headers, and `POST /ignore` without reading the body
+    async fn serve(limits: HttpLimits) -> SocketAddr {
+        let builder = HttpServer::builder()
+            .limits(limits)
+            .post("/ignore", |_| async {
+                HttpResponse::builder().body("ignored")
+            })
+            .post("/echo", |request: HttpRequest| async move {
+                let body = request.bytes().await?;
+                let mut response = HttpResponse::builder();
+
+                for trailer in request.trailers().iter() {
+                    response =
+                        response.header(&format!("Trailer-{}", trailer.name()), trailer.value());
+                }
+
+                response.body(body)
+            });
+
+        spawn(builder).await
+    }
+
+    /// Sends `request` on a new connection, closing it for writing, and reads until the server
+    /// closes it
+    async fn send(addr: SocketAddr, request: &[u8]) -> String {
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream.write_all(request).await.unwrap();
+        stream.shutdown().await.unwrap();
+
+        let mut response = vec![];
+        // The server may reset the connection after answering when part of the request is
+        // left unread, which still leaves the response readable
+        let _ = stream.read_to_end(&mut response).await;
+
+        String::from_utf8_lossy(&response).into_owned()
+    }
+
+    fn status(response: &str) -> &str {
+        response.split(' ').nth(1).unwrap_or_default()
+    }
+
+    fn body(response: &str) -> &str {
+        response.split_once("\r\n\r\n").unwrap_or_default().1
+    }
+
+    #[tokio::test]
+    async fn decodes_chunked_bodies_with_trailers() {
+        let addr = serve(HttpLimits::default()).await;
+        let response = send(
+            addr,
+            b"POST /echo HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n\
+              5;ext=1\r\nhello\r\n6\r\n world\r\n0\r\nChecksum: abc\r\n\r\n",
+        )
+        .await;
+
+        assert_eq!(status(&response), "200");
+        assert_eq!(body(&response), "hello world");
+        assert!(response.contains("Trailer-Checksum: abc\r\n"));
+    }
+
+    #[tokio::test]
+    async fn rejects_content_length_with_transfer_encoding() {
+        let addr = serve(HttpLimits::default()).await;
+        let response = send(
+            addr,
+            b"POST /echo HTTP/1.1\r\nContent-Length: 5\r\nTransfer-Encoding: chunked\r\n\r\n\
+              0\r\n\r\n",
+        )
+        .await;
+
+        assert_eq!(status(&response), "400");
+    }
+
+    #[tokio::test]
+    async fn rejects_transfer_codings_not_ending_with_chunked() {
+        let addr = serve(HttpLimits::default()).await;
+
+        let response = send(
+            addr,
+            b"POST /echo HTTP/1.1\r\nTransfer-Encoding: chunked, gzip\r\n\r\n",
+        )
+        .await;
+        assert_eq!(status(&response), "400");
+
+        let response = send(
+            addr,
+            b"POST /echo HTTP/1.1\r\nTransfer-Encoding: gzip\r\nTransfer-Encoding: chunked\r\n\r\n\
+              0\r\n\r\n",
+        )
+        .await;
+        assert_eq!(status(&response), "501");
+    }
+
+    #[tokio::test]
+    async fn rejects_conflicting_content_lengths() {
+        let addr = serve(HttpLimits::default()).await;
+
+        for headers in [
+            "Content-Length: 3, 4",
+            "Content-Length: 3\r\nContent-Length: 4",
+            "Content-Length: +3",
+            "Content-Length: abc",
+        ] {
+            let request = format!("POST /echo HTTP/1.1\r\n{headers}\r\n\r\nabcd");
+            assert_eq!(status(&send(addr, request.as_bytes()).await), "400");
+        }
+
+        let request = b"POST /echo HTTP/1.1\r\nContent-Length: 3, 3\r\n\r\nabc";
+        assert_eq!(body(&send(addr, request).await), "abc");
+    }
+
+    #[tokio::test]
+    async fn rejects_malformed_chunks() {
+        let addr = serve(HttpLimits::default()).await;
+
+        for chunks in [
+            "zz\r\nhello\r\n0\r\n\r\n",
+            "5\r\nhello world\r\n0\r\n\r\n",
+            "5\nhello\r\n0\r\n\r\n",
+            "0\r\nNo colon\r\n\r\n",
+        ] {
+            let request =
+                format!("POST /echo HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n{chunks}");
+            assert_eq!(
+                status(&send(addr, request.as_bytes()).await),
+                "400",
+                "{chunks:?}"
+            );
+        }
+    }
+
+    fn limits(max_body_size: usize) -> HttpLimits {
+        HttpLimits {
+            max_body_size,
+            ..HttpLimits::default()
         }
     }
+
+    #[tokio::test]
+    async fn skips_unread_bodies_between_requests() {
+        let addr = serve(HttpLimits::default()).await;
+        let response = send(
+            addr,
+            b"POST /ignore HTTP/1.1\r\nConnection: keep-alive\r\nContent-Length: 5\r\n\r\nhello\
+              POST /ignore HTTP/1.1\r\nConnection: keep-alive\r\nTransfer-Encoding: chunked\r\n\r\n\
+              5\r\nhello\r\n0\r\n\r\n\
+              POST /echo HTTP/1.1\r\nContent-Length: 3\r\n\r\nend",
+        )
+        .await;
+
+        assert_eq!(response.matches("HTTP/1.1 200").count(), 3);
+        assert_eq!(response.matches("\r\n\r\nignored").count(), 2);
+        assert!(response.ends_with("\r\n\r\nend"));
+    }
+
+    #[tokio::test]
+    async fn matches_framing_headers_case_insensitively() {
+        let addr = serve(HttpLimits::default()).await;
+        let response = send(
+            addr,
+            b"POST /echo HTTP/1.1\r\nconnection: Keep-Alive\r\ncontent-length: 5\r\n\r\nhello\
+              POST /echo HTTP/1.1\r\ntransfer-encoding: Chunked\r\n\r\n3\r\nend\r\n0\r\n\r\n",
+        )
+        .await;
+
+        assert_eq!(response.matches("HTTP/1.1 200").count(), 2);
+        assert!(response.contains("\r\n\r\nhelloHTTP/1.1 200"));
+        assert!(response.ends_with("\r\n\r\nend"));
+    }
+
+    #[tokio::test]
+    async fn refuses_announced_bodies_over_the_limit() {
+        let addr = serve(limits(4)).await;
+
+        // Refused from the headers alone, before the body is sent
+        let response = send(addr, b"POST /echo HTTP/1.1\r\nContent-Length: 5\r\n\r\n").await;
+        assert_eq!(status(&response), "413");
+
+        let response = send(
+            addr,
+            b"POST /echo HTTP/1.1\r\nContent-Length: 4\r\n\r\nabcd",
+        )
+        .await;
+        assert_eq!(body(&response), "abcd");
+    }
+
+    #[tokio::test]
+    async fn refuses_chunked_bodies_over_the_limit() {
+        let addr = serve(limits(8)).await;
+
+        let response = send(
+            addr,
+            b"POST /echo HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n\
+              5\r\nhello\r\n4\r\nover\r\n0\r\n\r\n",
+        )
+        .await;
+        assert_eq!(status(&response), "413");
+
+        let response = send(
+            addr,
+            b"POST /echo HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n\
+              5\r\nhello\r\n3\r\nend\r\n0\r\n\r\n",
+        )
+        .await;
+        assert_eq!(body(&response), "helloend");
+    }
+
+    /// A request to `POST /echo` with the header lines `headers` followed by an `X-Fill`
+    /// header, making the header section `size` bytes long, request line excluded
+    fn request_with_headers(headers: &str, size: usize) -> String {
+        let padding = "x".repeat(size - headers.len() - "X-Fill: \r\n".len());
+        format!("POST /echo HTTP/1.1\r\n{headers}X-Fill: {padding}\r\n\r\n")
+    }
+
+    #[tokio::test]
+    async fn limits_the_request_target() {
+        let addr = serve(HttpLimits {
+            max_request_target: 16,
+            ..HttpLimits::default()
+        })
+        .await;
+
+        let target = format!("/echo?{}", "a".repeat(10));
+        let request = format!("POST {target} HTTP/1.1\r\n\r\n");
+        assert_eq!(status(&send(addr, request.as_bytes()).await), "200");
+
+        let request = format!("POST {target}a HTTP/1.1\r\n\r\n");
+        assert_eq!(status(&send(addr, request.as_bytes()).await), "414");
+
+        // Past the room left for the method and version, the line is refused before it ends
+        let request = format!("POST {target}{} HTTP/1.1\r\n\r\n", "a".repeat(64));
+        assert_eq!(status(&send(addr, request.as_bytes()).await), "414");
+    }
+
+    #[tokio::test]
+    async fn limits_the_size_of_the_headers() {
+        let addr = serve(HttpLimits {
+            max_header_size: 64,
+            ..HttpLimits::default()
+        })
+        .await;
+
+        let send_headers = |headers: &'static str, size| async move {
+            status(&send(addr, request_with_headers(headers, size).as_bytes()).await).to_string()
+        };
+
+        assert_eq!(send_headers("", 64).await, "200");
+        assert_eq!(send_headers("A: 1\r\n", 64).await, "200");
+
+        // One byte over, within a line still short enough to be read
+        assert_eq!(send_headers("", 65).await, "431");
+        assert_eq!(send_headers("A: 1\r\n", 65).await, "431");
+
+        // A line longer than what is left, refused before it ends
+        assert_eq!(send_headers("", 67).await, "431");
+        assert_eq!(send_headers("A: 1\r\n", 67).await, "431");
+    }
+
+    #[tokio::test]
+    async fn limits_the_number_of_headers() {
+        let addr = serve(HttpLimits {
+            max_headers: 2,
+            ..HttpLimits::default()
+        })
+        .await;
+
+        let response = send(addr, b"POST /echo HTTP/1.1\r\nA: 1\r\nB: 2\r\n\r\n").await;
+        assert_eq!(status(&response), "200");
+
+        let response = send(addr, b"POST /echo HTTP/1.1\r\nA: 1\r\nB: 2\r\nC: 3\r\n\r\n").await;
+        assert_eq!(status(&response), "431");
+    }
+
+    #[test]
+    fn fills_missing_limits_with_defaults() {
+        let limits: HttpLimits = serde_json::from_str(r#"{ "max_headers": 10 }"#).unwrap();
+
+        assert_eq!(limits.max_headers, 10);
+        assert_eq!(limits.max_body_size, HttpLimits::default().max_body_size);
+    }
+
+    #[tokio::test]
+    async fn answers_invalid_versions() {
+        let addr = serve(HttpLimits::default()).await;
+
+        let response = send(addr, b"POST /echo X\r\n\r\n").await;
+        assert_eq!(status(&response), "400");
+
+        let response = send(addr, b"POST /echo HTTP/2.0\r\n\r\n").await;
+        assert_eq!(status(&response), "505");
+
+        // The server survives the malformed requests
+        let response = send(addr, b"POST /echo HTTP/1.1\r\n\r\n").await;
+        assert_eq!(status(&response), "200");
+    }
+
+    /// Binds `builder` to a free port and serves it in the background
+    async fn spawn(builder: HttpServerBuilder) -> SocketAddr {
+        let server = builder.bind("127.0.0.1:0").await.unwrap();
+        let addr = server.local_addr().unwrap();
+        tokio::spawn(async move { server.listen().await });
+        addr
+    }
+
+    #[tokio::test]
+    async fn runs_capturing_closures_with_shared_state() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        struct Visits(AtomicUsize);
+
+        let greeting = String::from("hello");
+        let addr = spawn(
+            HttpServer::builder()
+                .with_state(Visits(AtomicUsize::new(0)))
+                .get("/visit", move |request: HttpRequest| {
+                    let visits = request.state::<Visits>().unwrap();
+                    let count = visits.0.fetch_add(1, Ordering::SeqCst) + 1;
+                    let body = format!("{greeting} {count}");
+                    async move { HttpResponse::builder().body(body) }
+                }),
+        )
+        .await;
+
+        for count in 1..=3 {
+            let response = send(addr, b"GET /visit HTTP/1.1\r\n\r\n").await;
+            assert_eq!(body(&response), format!("hello {count}"));
+        }
+    }
+
+    #[tokio::test]
+    async fn reports_missing_state() {
+        struct Missing;
+
+        let addr = spawn(HttpServer::builder().with_state(1_u32).get(
+            "/state",
+            |request: HttpRequest| async move {
+                assert!(request.state::<u64>().is_none());
+                request
+                    .state::<Missing>()
+                    .ok_or(HttpError::InternalServerError {
+                        message: "Missing state".to_string(),
+                    })?;
+                HttpResponse::builder().body("unreachable")
+            },
+        ))
+        .await;
+
+        let response = send(addr, b"GET /state HTTP/1.1\r\n\r\n").await;
+        assert_eq!(status(&response), "500");
+    }
+
+    #[tokio::test]
+    async fn runs_independent_servers_in_one_process() {
+        let public = spawn(HttpServer::builder().with_state("public").get(
+            "/name",
+            |request: HttpRequest| async move {
+                HttpResponse::builder().body(*request.state::<&str>().unwrap())
+            },
+        ))
+        .await;
+        let admin = spawn(
+            HttpServer::builder()
+                .with_state("admin")
+                .get("/name", |request: HttpRequest| async move {
+                    HttpResponse::builder().body(*request.state::<&str>().unwrap())
+                })
+                .get("/admin", |_| async { HttpResponse::builder().body("ok") }),
+        )
+        .await;
+
+        let request = b"GET /name HTTP/1.1\r\n\r\n";
+        assert_eq!(body(&send(public, request).await), "public");
+        assert_eq!(body(&send(admin, request).await), "admin");
+
+        let request = b"GET /admin HTTP/1.1\r\n\r\n";
+        assert_eq!(status(&send(public, request).await), "404");
+        assert_eq!(status(&send(admin, request).await), "200");
+    }
+
+    #[tokio::test]
+    async fn answers_head_without_a_body() {
+        let addr = spawn(
+            HttpServer::builder().get("/page", |_| async { HttpResponse::builder().body("hello") }),
+        )
+        .await;
+
+        let response = send(
+            addr,
+            b"HEAD /page HTTP/1.1\r\nConnection: keep-alive\r\n\r\n\
+              GET /page HTTP/1.1\r\n\r\n",
+        )
+        .await;
+
+        // The HEAD response keeps the Content-Length of the GET response, and the GET response
+        // follows right after its headers
+        let (head, get) = response.split_once("\r\n\r\n").unwrap();
+        assert!(head.starts_with("HTTP/1.1 200"));
+        assert!(head.contains("\r\nContent-Length: 5"));
+        assert!(get.starts_with("HTTP/1.1 200"));
+        assert_eq!(body(get), "hello");
+    }
+
+    /// Starts a server answering `GET /stream` with a streaming body, `GET /broken` with a
+    /// stream failing after its first chunk and `GET /page` with a buffered body
+    async fn serve_streams() -> SocketAddr {
+        let chunks = |chunks: Vec<std::io::Result<&'static str>>| {
+            let chunks = chunks.into_iter().map(|chunk| chunk.map(Vec::from));
+            HttpResponse::ok(HttpBody::stream(futures::stream::iter(chunks)))
+        };
+
+        spawn(
+            HttpServer::builder()
+                .get("/stream", move |_| async move {
+                    chunks(vec![Ok("hello "), Ok("world")])
+                })
+                .get("/broken", move |_| async move {
+                    chunks(vec![Ok("partial"), Err(ErrorKind::BrokenPipe.into())])
+                })
+                .get("/page", |_| async { HttpResponse::ok("page") }),
+        )
+        .await
+    }
+
+    #[tokio::test]
+    async fn streams_chunked_bodies_to_http_1_1() {
+        let addr = serve_streams().await;
+        let response = send(
+            addr,
+            b"GET /stream HTTP/1.1\r\nConnection: keep-alive\r\n\r\n\
+              GET /page HTTP/1.1\r\n\r\n",
+        )
+        .await;
+
+        let (stream, page) = response.split_once("0\r\n\r\n").unwrap();
+        assert!(stream.contains("\r\nTransfer-Encoding: chunked\r\n"));
+        assert!(!stream.contains("Content-Length"));
+        assert_eq!(body(stream), "6\r\nhello \r\n5\r\nworld\r\n");
+
+        // The connection is reused once the last chunk is written
+        assert_eq!(status(page), "200");
+        assert_eq!(body(page), "page");
+    }
+
+    #[tokio::test]
+    async fn streams_close_delimited_bodies_to_http_1_0() {
+        let addr = serve_streams().await;
+        let response = send(
+            addr,
+            b"GET /stream HTTP/1.0\r\nConnection: keep-alive\r\n\r\n\
+              GET /page HTTP/1.0\r\n\r\n",
+        )
+        .await;
+
+        assert!(!response.contains("Transfer-Encoding"));
+        assert!(!response.contains("Content-Length"));
+        // The connection is closed to mark the end of the body, so the second request is
+        // never answered
+        assert_eq!(body(&response), "hello world");
+    }
+
+    #[tokio::test]
+    async fn closes_the_connection_after_a_stream_error() {
+        let addr = serve_streams().await;
+        let response = send(
+            addr,
+            b"GET /broken HTTP/1.1\r\nConnection: keep-alive\r\n\r\n\
+              GET /page HTTP/1.1\r\n\r\n",
+        )
+        .await;
+
+        // Without the last chunk the client can tell the body is incomplete
+        assert_eq!(body(&response), "7\r\npartial\r\n");
+        assert_eq!(response.matches("HTTP/1.1").count(), 1);
+    }
+
+    #[tokio::test]
+    async fn answers_head_on_streams_with_the_framing_of_get() {
+        let addr = serve_streams().await;
+
+        let response = send(
+            addr,
+            b"HEAD /stream HTTP/1.1\r\nConnection: keep-alive\r\n\r\n\
+              GET /page HTTP/1.1\r\n\r\n",
+        )
+        .await;
+        let (head, page) = response.split_once("\r\n\r\n").unwrap();
+        assert!(head.contains("\r\nTransfer-Encoding: chunked"));
+        assert!(!head.contains("Content-Length"));
+        assert!(page.starts_with("HTTP/1.1 200"));
+        assert_eq!(body(page), "page");
+
+        let response = send(addr, b"HEAD /stream HTTP/1.0\r\n\r\n").await;
+        assert!(!response.contains("Transfer-Encoding"));
+        assert!(!response.contains("Content-Length"));
+        assert_eq!(body(&response), "");
+    }
 }
@@ -0,0 +1,774 @@
+use crate::{HttpCookie, HttpError, HttpRequest, HttpResponse, Middleware, Next, SameSite};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{DateTime, Duration, Utc};
+use futures::future::BoxFuture;
+use rand::RngCore;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+use std::{
+    collections::HashMap,
+    io::ErrorKind,
+    path::PathBuf,
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+const SESSION_ID_BYTES: usize = 32;
+/// Length of a base64 encoded session ID without padding
+const SESSION_ID_LENGTH: usize = 43;
+/// Minimum time between two sweeps of the expired sessions of a store
+const SWEEP_INTERVAL_SECONDS: i64 = 60;
+
+/// A session as kept by a [`SessionStore`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionRecord {
+    pub data: HashMap<String, Value>,
+    pub created_at: DateTime<Utc>,
+    pub last_accessed: DateTime<Utc>,
+}
+
+/// Storage for session data, keyed by session ID
+pub trait SessionStore: Send + Sync + 'static {
+    fn load<'a>(&'a self, id: &'a str) -> BoxFuture<'a, Result<Option<SessionRecord>, HttpError>>;
+
+    fn save<'a>(
+        &'a self,
+        id: &'a str,
+        record: &'a SessionRecord,
+    ) -> BoxFuture<'a, Result<(), HttpError>>;
+
+    fn destroy<'a>(&'a self, id: &'a str) -> BoxFuture<'a, Result<(), HttpError>>;
+
+    /// Deletes every session last accessed before `accessed_before` or created before
+    /// `created_before`, including the ones whose clients never come back
+    fn remove_expired(
+        &self,
+        accessed_before: DateTime<Utc>,
+        created_before: DateTime<Utc>,
+    ) -> BoxFuture<'_, Result<(), HttpError>>;
+}
+
+impl SessionRecord {
+    fn is_expired(&self, accessed_before: DateTime<Utc>, created_before: DateTime<Utc>) -> bool {
+        self.last_accessed < accessed_before || self.created_at < created_before
+    }
+}
+
+/// Keeps sessions in memory, so they are lost when the server restarts
+#[derive(Debug, Default)]
+pub struct MemorySessionStore {
+    sessions: Mutex<HashMap<String, SessionRecord>>,
+}
+
+impl MemorySessionStore {
+    pub fn new() -> MemorySessionStore {
+        MemorySessionStore::default()
+    }
+
+    fn sessions(&self) -> MutexGuard<'_, HashMap<String, SessionRecord>> {
+        self.sessions
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl SessionStore for MemorySessionStore {
+    fn load<'a>(&'a self, id: &'a str) -> BoxFuture<'a, Result<Option<SessionRecord>, HttpError>> {
+        Box::pin(async move { Ok(self.sessions().get(id).cloned()) })
+    }
+
+    fn save<'a>(
+        &'a self,
+        id: &'a str,
+        record: &'a SessionRecord,
+    ) -> BoxFuture<'a, Result<(), HttpError>> {
+        Box::pin(async move {
+            self.sessions().insert(id.to_string(), record.clone());
+            Ok(())
+        })
+    }
+
+    fn destroy<'a>(&'a self, id: &'a str) -> BoxFuture<'a, Result<(), HttpError>> {
+        Box::pin(async move {
+            self.sessions().remove(id);
+            Ok(())
+        })
+    }
+
+    fn remove_expired(
+        &self,
+        accessed_before: DateTime<Utc>,
+        created_before: DateTime<Utc>,
+    ) -> BoxFuture<'_, Result<(), HttpError>> {
+        Box::pin(async move {
+            self.sessions()
+                .retain(|_, record| !record.is_expired(accessed_before, created_before));
+            Ok(())
+        })
+    }
+}
+
+/// Keeps each session as a JSON file in a directory
+#[derive(Debug, Clone)]
+pub struct FileSessionStore {
+    directory: PathBuf,
+}
+
+impl FileSessionStore {
+    pub fn new(directory: impl Into<PathBuf>) -> FileSessionStore {
+        FileSessionStore {
+            directory: directory.into(),
+        }
+    }
+
+    fn path(&self, id: &str) -> Result<PathBuf, HttpError> {
+        // IDs come from clients, so never let one name a file outside the directory
+        if !is_valid_session_id(id) {
+            return Err(HttpError::BadRequest {
+                message: "Invalid session ID".to_string(),
+            });
+        }
+
+        Ok(self.directory.join(format!("{id}.json")))
+    }
+}
+
+impl SessionStore for FileSessionStore {
+    fn load<'a>(&'a self, id: &'a str) -> BoxFuture<'a, Result<Option<SessionRecord>, HttpError>> {
+        Box::pin(async move {
+            let contents = match tokio::fs::read(self.path(id)?).await {
+                Ok(contents) => contents,
+                Err(error) if error.kind() == ErrorKind::NotFound => return Ok(None),
+                Err(error) => return Err(error.into()),
+            };
+
+            // A corrupted file is treated like a missing session
+            Ok(serde_json::from_slice(&contents).ok())
+        })
+    }
+
+    fn save<'a>(
+        &'a self,
+        id: &'a str,
+        record: &'a SessionRecord,
+    ) -> BoxFuture<'a, Result<(), HttpError>> {
+        Box::pin(async move {
+            let path = self.path(id)?;
+            let contents =
+                serde_json::to_vec(record).map_err(|error| HttpError::InternalServerError {
+                    message: format!("Failed to serialize session: {error}"),
+                })?;
+
+            tokio::fs::create_dir_all(&self.directory).await?;
+
+            // Write to a temporary file first so readers never see a partial session
+            let temporary_path = path.with_extension("json.tmp");
+            tokio::fs::write(&temporary_path, contents).await?;
+            tokio::fs::rename(temporary_path, path).await?;
+
+            Ok(())
+        })
+    }
+
+    fn destroy<'a>(&'a self, id: &'a str) -> BoxFuture<'a, Result<(), HttpError>> {
+        Box::pin(async move {
+            match tokio::fs::remove_file(self.path(id)?).await {
+                Err(error) if error.kind() != ErrorKind::NotFound => Err(error.into()),
+                _ => Ok(()),
+            }
+        })
+    }
+
+    fn remove_expired(
+        &self,
+        accessed_before: DateTime<Utc>,
+        created_before: DateTime<Utc>,
+    ) -> BoxFuture<'_, Result<(), HttpError>> {
+        Box::pin(async move {
+            let mut entries = match tokio::fs::read_dir(&self.directory).await {
+                Ok(entries) => entries,
+                Err(error) if error.kind() == ErrorKind::NotFound => return Ok(()),
+                Err(error) => return Err(error.into()),
+            };
+
+            while let Some(entry) = entries.next_entry().await? {
+                let path = entry.path();
+
+                let is_session = path
+                    .extension()
+                    .is_some_and(|extension| extension == "json")
+                    && path
+                        .file_stem()
+                        .and_then(|stem| stem.to_str())
+                        .is_some_and(is_valid_session_id);
+
+                if !is_session {
+                    continue;
+                }
+
+                // A file removed meanwhile, e.g. by a logout, is no longer a concern
+                let contents = match tokio::fs::read(&path).await {
+                    Ok(contents) => contents,
+                    Err(error) if error.kind() == ErrorKind::NotFound => continue,
+                    Err(error) => return Err(error.into()),
+                };
+
+                // A corrupted file can never be loaded, so it is removed as well
+                let expired = serde_json::from_slice::<SessionRecord>(&contents)
+                    .map_or(true, |record| {
+                        record.is_expired(accessed_before, created_before)
+                    });
+
+                if expired {
+                    match tokio::fs::remove_file(&path).await {
+                        Err(error) if error.kind() != ErrorKind::NotFound => {
+                            return Err(error.into())
+                        }
+                        _ => {}
+                    }
+                }
+            }
+
+            Ok(())
+        })
+    }
+}
+
+#[derive(Debug, Default)]
+struct SessionState {
+    data: HashMap<String, Value>,
+    regenerate: bool,
+    destroyed: bool,
+}
+
+/// The session of the current request, available through [`HttpRequest::session`].
+///
+/// Changes are saved to the [`SessionStore`] by [`SessionMiddleware`] once the handler has
+/// returned.
+#[derive(Debug, Clone, Default)]
+pub struct Session {
+    state: Arc<Mutex<SessionState>>,
+}
+
+impl Session {
+    fn new(data: HashMap<String, Value>) -> Session {
+        Session {
+            state: Arc::new(Mutex::new(SessionState {
+                data,
+                ..Default::default()
+            })),
+        }
+    }
+
+    fn state(&self) -> MutexGuard<'_, SessionState> {
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// The value stored under `key`, or `None` if there is none or it is not a `T`
+    pub fn get<T: DeserializeOwned>(&self, key: &str) -> Option<T> {
+        let value = self.state().data.get(key)?.clone();
+        serde_json::from_value(value).ok()
+    }
+
+    pub fn insert<T: Serialize>(&self, key: &str, value: &T) -> Result<(), HttpError> {
+        let value =
+            serde_json::to_value(value).map_err(|error| HttpError::InternalServerError {
+                message: format!("Failed to serialize session value `{key}`: {error}"),
+            })?;
+
+        self.state().data.insert(key.to_string(), value);
+        Ok(())
+    }
+
+    pub fn remove(&self, key: &str) {
+        self.state().data.remove(key);
+    }
+
+    pub fn clear(&self) {
+        self.state().data.clear();
+    }
+
+    /// Moves the session to a new ID, keeping its data. Call this when a user logs in so
+    /// an ID known to someone else before login cannot be used afterwards.
+    pub fn regenerate(&self) {
+        self.state().regenerate = true;
+    }
+
+    /// Deletes the session from the store and removes its cookie
+    pub fn destroy(&self) {
+        let mut state = self.state();
+        state.data.clear();
+        state.destroyed = true;
+    }
+}
+
+/// Loads the session named by a cookie before the handler runs and saves it afterwards.
+///
+/// Sessions expire once they have been idle for longer than the idle timeout or have existed
+/// for longer than the absolute timeout, whichever comes first. A new session is only stored,
+/// and its cookie only set, once something has been inserted into it.
+#[derive(Clone)]
+pub struct SessionMiddleware {
+    store: Arc<dyn SessionStore>,
+    cookie_name: String,
+    idle_timeout: Duration,
+    absolute_timeout: Duration,
+    secure: bool,
+    /// When the store was last swept of expired sessions, shared by every clone
+    last_sweep: Arc<Mutex<DateTime<Utc>>>,
+}
+
+impl SessionMiddleware {
+    pub fn new<S: SessionStore>(store: S) -> SessionMiddleware {
+        SessionMiddleware {
+            store: Arc::new(store),
+            cookie_name: "session".to_string(),
+            idle_timeout: Duration::minutes(30),
+            absolute_timeout: Duration::hours(24),
+            secure: false,
+            last_sweep: Arc::new(Mutex::new(Utc::now())),
+        }
+    }
+
+    /// Names the session cookie, failing with [`HttpError::InternalServerError`] unless
+    /// `cookie_name` is a valid cookie name, see
+    /// [`HttpCookieBuilder::build`](crate::HttpCookieBuilder::build)
+    pub fn cookie_name(mut self, cookie_name: &str) -> Result<SessionMiddleware, HttpError> {
+        HttpCookie::builder(cookie_name, "").build()?;
+        self.cookie_name = cookie_name.to_string();
+        Ok(self)
+    }
+
+    pub fn idle_timeout(mut self, idle_timeout: Duration) -> SessionMiddleware {
+        self.idle_timeout = idle_timeout;
+        self
+    }
+
+    pub fn absolute_timeout(mut self, absolute_timeout: Duration) -> SessionMiddleware {
+        self.absolute_timeout = absolute_timeout;
+        self
+    }
+
+    /// Marks the session cookie `Secure`, which should be enabled behind HTTPS
+    pub fn secure(mut self, secure: bool) -> SessionMiddleware {
+        self.secure = secure;
+        self
+    }
+
+    fn is_expired(&self, record: &SessionRecord, now: DateTime<Utc>) -> bool {
+        record.is_expired(now - self.idle_timeout, now - self.absolute_timeout)
+    }
+
+    /// Removes the expired sessions of the store in the background, at most once per
+    /// [`SWEEP_INTERVAL_SECONDS`], as the sessions of clients that never return are
+    /// otherwise kept forever
+    fn sweep(&self, now: DateTime<Utc>) {
+        {
+            let mut last_sweep = self
+                .last_sweep
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+            if now - *last_sweep < Duration::seconds(SWEEP_INTERVAL_SECONDS) {
+                return;
+            }
+
+            *last_sweep = now;
+        }
+
+        let store = self.store.clone();
+        let accessed_before = now - self.idle_timeout;
+        let created_before = now - self.absolute_timeout;
+
+        tokio::spawn(async move {
+            if let Err(error) = store.remove_expired(accessed_before, created_before).await {
+                println!("Failed to remove expired sessions due to error: {error}");
+            }
+        });
+    }
+
+    fn cookie(&self, id: &str) -> Result<HttpCookie, HttpError> {
+        HttpCookie::builder(&self.cookie_name, id)
+            .path("/")
+            .http_only(true)
+            .same_site(SameSite::Lax)
+            .secure(self.secure)
+            .build()
+    }
+
+    async fn run(&self, mut request: HttpRequest, next: Next) -> Result<HttpResponse, HttpError> {
+        let now = Utc::now();
+        self.sweep(now);
+
+        let id = request
+            .get_cookie(&self.cookie_name)
+            .map(|cookie| cookie.value().to_string())
+            .filter(|id| is_valid_session_id(id));
+
+        let mut record = match &id {
+            Some(id) => self.store.load(id).await?,
+            None => None,
+        };
+
+        if let (Some(id), Some(expired)) = (&id, &record) {
+            if self.is_expired(expired, now) {
+                self.store.destroy(id).await?;
+                record = None;
+            }
+        }
+
+        // An unknown or expired ID is never reused, so clients cannot choose their own
+        let id = id.filter(|_| record.is_some());
+        let created_at = record.as_ref().map_or(now, |record| record.created_at);
+
+        let session = Session::new(record.map(|record| record.data).unwrap_or_default());
+        request.extensions_mut().insert(session.clone());
+
+        let mut response = next.run(request).await;
+
+        let state = std::mem::take(&mut *session.state());
+
+        if state.destroyed || (id.is_none() && state.data.is_empty()) {
+            if let Some(id) = &id {
+                self.store.destroy(id).await?;
+                response.remove_cookie(&self.cookie("")?);
+            }
+
+            return Ok(response);
+        }
+
+        let new_id = match id {
+            Some(id) if state.regenerate => {
+                self.store.destroy(&id).await?;
+                None
+            }
+            id => id,
+        };
+
+        let id = match new_id {
+            Some(id) => id,
+            None => {
+                let id = generate_session_id();
+                response.set_cookie(&self.cookie(&id)?);
+                id
+            }
+        };
+
+        let record = SessionRecord {
+            data: state.data,
+            created_at,
+            last_accessed: now,
+        };
+
+        self.store.save(&id, &record).await?;
+
+        Ok(response)
+    }
+}
+
+impl Middleware for SessionMiddleware {
+    fn handle(&self, request: HttpRequest, next: Next) -> BoxFuture<'static, HttpResponse> {
+        let middleware = self.clone();
+
+        Box::pin(async move {
+            match middleware.run(request, next).await {
+                Ok(response) => response,
+                Err(error) => error.as_response(),
+            }
+        })
+    }
+}
+
+fn generate_session_id() -> String {
+    let mut id = [0; SESSION_ID_BYTES];
+    rand::thread_rng().fill_bytes(&mut id);
+    URL_SAFE_NO_PAD.encode(id)
+}
+
+fn is_valid_session_id(id: &str) -> bool {
+    id.len() == SESSION_ID_LENGTH
+        && id
+            .bytes()
+            .all(|byte| byte.is_ascii_alphanumeric() || byte == b'-' || byte == b'_')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{HttpBody, HttpRouter};
+
+    #[test]
+    fn rejects_invalid_cookie_names() {
+        let middleware = || SessionMiddleware::new(MemorySessionStore::new());
+
+        assert!(middleware().cookie_name("my session").is_err());
+        assert!(middleware().cookie_name("").is_err());
+        assert!(middleware().cookie_name("my_session").is_ok());
+    }
+
+    fn record(now: DateTime<Utc>, age: Duration, idle: Duration) -> SessionRecord {
+        SessionRecord {
+            data: HashMap::new(),
+            created_at: now - age,
+            last_accessed: now - idle,
+        }
+    }
+
+    /// Saves a fresh, an idle and an old session, then sweeps with a 30 minute idle timeout
+    /// and a 24 hour absolute timeout, returning the IDs
+    async fn sweep<S: SessionStore>(store: &S) -> [String; 3] {
+        let now = Utc::now();
+        let ids = [(), (), ()].map(|_| generate_session_id());
+
+        let records = [
+            record(now, Duration::hours(1), Duration::minutes(1)),
+            record(now, Duration::hours(1), Duration::minutes(31)),
+            record(now, Duration::hours(25), Duration::minutes(1)),
+        ];
+
+        for (id, record) in ids.iter().zip(&records) {
+            store.save(id, record).await.unwrap();
+        }
+
+        store
+            .remove_expired(now - Duration::minutes(30), now - Duration::hours(24))
+            .await
+            .unwrap();
+
+        ids
+    }
+
+    #[tokio::test]
+    async fn memory_store_removes_expired_sessions() {
+        let store = MemorySessionStore::new();
+        let [fresh, idle, old] = sweep(&store).await;
+
+        assert!(store.load(&fresh).await.unwrap().is_some());
+        assert!(store.load(&idle).await.unwrap().is_none());
+        assert!(store.load(&old).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn file_store_removes_expired_sessions() {
+        let directory = std::env::temp_dir().join(format!("hyperion-{}", generate_session_id()));
+        let store = FileSessionStore::new(&directory);
+
+        let corrupted = directory.join(format!("{}.json", generate_session_id()));
+        let unrelated = directory.join("notes.txt");
+        std::fs::create_dir_all(&directory).unwrap();
+        std::fs::write(&corrupted, "{").unwrap();
+        std::fs::write(&unrelated, "{").unwrap();
+
+        let [fresh, idle, old] = sweep(&store).await;
+
+        assert!(store.load(&fresh).await.unwrap().is_some());
+        assert!(store.load(&idle).await.unwrap().is_none());
+        assert!(store.load(&old).await.unwrap().is_none());
+        assert!(!corrupted.exists());
+        assert!(unrelated.exists());
+
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+
+    /// Routes reading, incrementing and dropping a counter kept in the session
+    fn router<S: SessionStore>(store: S) -> HttpRouter {
+        let mut router = HttpRouter::new();
+        router.middleware(SessionMiddleware::new(store));
+
+        router.get("/count", |request: HttpRequest| async move {
+            let count = request.session()?.get::<u32>("count");
+            let body = count.map_or("none".to_string(), |count| count.to_string());
+            Ok::<_, HttpError>(HttpResponse::ok(body))
+        });
+        router.post("/count", |request: HttpRequest| async move {
+            let session = request.session()?;
+            let count = session.get::<u32>("count").unwrap_or(0) + 1;
+            session.insert("count", &count)?;
+            Ok::<_, HttpError>(HttpResponse::ok(count.to_string()))
+        });
+        router.post("/login", |request: HttpRequest| async move {
+            request.session()?.regenerate();
+            Ok::<_, HttpError>(HttpResponse::ok(""))
+        });
+        router.post("/logout", |request: HttpRequest| async move {
+            request.session()?.destroy();
+            Ok::<_, HttpError>(HttpResponse::ok(""))
+        });
+
+        router
+    }
+
+    /// Sends a request with the session cookie `id`, returning the body and the
+    /// `Set-Cookie` header
+    async fn send(
+        router: &HttpRouter,
+        method: &str,
+        target: &str,
+        id: Option<&str>,
+    ) -> (String, Option<String>) {
+        let cookie = id.map_or(String::new(), |id| format!("\r\nCookie: session={id}"));
+
+        let request = HttpRequest::new(
+            format!("{method} {target} HTTP/1.1\r\nHost: localhost{cookie}").into_bytes(),
+        )
+        .unwrap();
+        let response = router.process_request(request).await;
+        assert_eq!(response.status().as_u16(), 200);
+
+        let body = response
+            .body()
+            .and_then(HttpBody::as_bytes)
+            .unwrap_or_default();
+        let set_cookie = response.get_header("Set-Cookie").map(str::to_string);
+
+        (String::from_utf8(body).unwrap(), set_cookie)
+    }
+
+    /// The session ID set by a `Set-Cookie` header
+    fn session_id(set_cookie: &str) -> String {
+        let (pair, _) = set_cookie.split_once(';').unwrap();
+        pair.strip_prefix("session=").unwrap().to_string()
+    }
+
+    fn temp_directory() -> PathBuf {
+        std::env::temp_dir().join(format!("hyperion-{}", generate_session_id()))
+    }
+
+    async fn persists_sessions(router: HttpRouter) {
+        assert_eq!(
+            send(&router, "GET", "/count", None).await,
+            ("none".to_string(), None)
+        );
+
+        let (body, set_cookie) = send(&router, "POST", "/count", None).await;
+        let set_cookie = set_cookie.unwrap();
+        let id = session_id(&set_cookie);
+        assert_eq!(body, "1");
+        assert!(is_valid_session_id(&id));
+        assert_eq!(
+            set_cookie,
+            format!("session={id}; Path=/; HttpOnly; SameSite=Lax")
+        );
+
+        assert_eq!(
+            send(&router, "POST", "/count", Some(&id)).await,
+            ("2".to_string(), None)
+        );
+        assert_eq!(
+            send(&router, "GET", "/count", Some(&id)).await,
+            ("2".to_string(), None)
+        );
+    }
+
+    #[tokio::test]
+    async fn persists_sessions_in_memory() {
+        persists_sessions(router(MemorySessionStore::new())).await;
+    }
+
+    #[tokio::test]
+    async fn persists_sessions_in_files() {
+        let directory = temp_directory();
+        persists_sessions(router(FileSessionStore::new(&directory))).await;
+
+        assert_eq!(std::fs::read_dir(&directory).unwrap().count(), 1);
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[tokio::test]
+    async fn ignores_unknown_session_ids() {
+        let router = router(MemorySessionStore::new());
+        let unknown = generate_session_id();
+
+        assert_eq!(
+            send(&router, "GET", "/count", Some(&unknown)).await,
+            ("none".to_string(), None)
+        );
+
+        let (_, set_cookie) = send(&router, "POST", "/count", Some(&unknown)).await;
+        assert_ne!(session_id(&set_cookie.unwrap()), unknown);
+    }
+
+    #[tokio::test]
+    async fn regenerates_session_ids() {
+        let router = router(MemorySessionStore::new());
+
+        let (_, set_cookie) = send(&router, "POST", "/count", None).await;
+        let old_id = session_id(&set_cookie.unwrap());
+
+        let (_, set_cookie) = send(&router, "POST", "/login", Some(&old_id)).await;
+        let new_id = session_id(&set_cookie.unwrap());
+        assert_ne!(new_id, old_id);
+
+        assert_eq!(
+            send(&router, "GET", "/count", Some(&new_id)).await,
+            ("1".to_string(), None)
+        );
+        assert_eq!(
+            send(&router, "GET", "/count", Some(&old_id)).await,
+            ("none".to_string(), None)
+        );
+    }
+
+    #[tokio::test]
+    async fn expires_sessions_at_request_time() {
+        let directory = temp_directory();
+        let store = FileSessionStore::new(&directory);
+        let router = router(store.clone());
+        let now = Utc::now();
+
+        let mut ids = vec![];
+        for record in [
+            record(now, Duration::hours(1), Duration::minutes(29)),
+            record(now, Duration::hours(1), Duration::minutes(31)),
+            record(now, Duration::hours(25), Duration::minutes(1)),
+        ] {
+            let id = generate_session_id();
+            let record = SessionRecord {
+                data: HashMap::from([("count".to_string(), Value::from(1))]),
+                ..record
+            };
+            store.save(&id, &record).await.unwrap();
+            ids.push(id);
+        }
+
+        let [fresh, idle, old] = <[String; 3]>::try_from(ids).unwrap();
+
+        assert_eq!(
+            send(&router, "GET", "/count", Some(&fresh)).await,
+            ("1".to_string(), None)
+        );
+        let record = store.load(&fresh).await.unwrap().unwrap();
+        assert!(record.last_accessed >= now);
+
+        for id in [idle, old] {
+            assert_eq!(
+                send(&router, "GET", "/count", Some(&id)).await,
+                ("none".to_string(), None)
+            );
+            assert!(store.load(&id).await.unwrap().is_none());
+        }
+
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[tokio::test]
+    async fn destroys_sessions() {
+        let router = router(MemorySessionStore::new());
+
+        let (_, set_cookie) = send(&router, "POST", "/count", None).await;
+        let id = session_id(&set_cookie.unwrap());
+
+        let (_, set_cookie) = send(&router, "POST", "/logout", Some(&id)).await;
+        assert_eq!(
+            set_cookie.unwrap(),
+            "session=; Expires=Thu, 01 Jan 1970 00:00:00 GMT; Max-Age=0; Path=/; HttpOnly; \
+             SameSite=Lax"
+        );
+
+        assert_eq!(
+            send(&router, "GET", "/count", Some(&id)).await,
+            ("none".to_string(), None)
+        );
+    }
+}
@@ -0,0 +1,228 @@
+use crate::HttpError;
+use std::fmt::Display;
+
+/// The status code of a response, any three digit number from 100 to 999
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct StatusCode(u16);
+
+macro_rules! status_codes {
+    ($(($code:literal, $name:ident, $reason_phrase:literal),)+) => {
+        impl StatusCode {
+            $(
+                #[doc = concat!("`", $code, " ", $reason_phrase, "`")]
+                pub const $name: StatusCode = StatusCode($code);
+            )+
+
+            /// The reason phrase registered with IANA, or `None` for an unregistered code
+            pub fn reason_phrase(&self) -> Option<&'static str> {
+                match self.0 {
+                    $($code => Some($reason_phrase),)+
+                    _ => None,
+                }
+            }
+        }
+    };
+}
+
+status_codes! {
+    (100, CONTINUE, "Continue"),
+    (101, SWITCHING_PROTOCOLS, "Switching Protocols"),
+    (102, PROCESSING, "Processing"),
+    (103, EARLY_HINTS, "Early Hints"),
+    (200, OK, "OK"),
+    (201, CREATED, "Created"),
+    (202, ACCEPTED, "Accepted"),
+    (203, NON_AUTHORITATIVE_INFORMATION, "Non-Authoritative Information"),
+    (204, NO_CONTENT, "No Content"),
+    (205, RESET_CONTENT, "Reset Content"),
+    (206, PARTIAL_CONTENT, "Partial Content"),
+    (207, MULTI_STATUS, "Multi-Status"),
+    (208, ALREADY_REPORTED, "Already Reported"),
+    (226, IM_USED, "IM Used"),
+    (300, MULTIPLE_CHOICES, "Multiple Choices"),
+    (301, MOVED_PERMANENTLY, "Moved Permanently"),
+    (302, FOUND, "Found"),
+    (303, SEE_OTHER, "See Other"),
+    (304, NOT_MODIFIED, "Not Modified"),
+    (305, USE_PROXY, "Use Proxy"),
+    (307, TEMPORARY_REDIRECT, "Temporary Redirect"),
+    (308, PERMANENT_REDIRECT, "Permanent Redirect"),
+    (400, BAD_REQUEST, "Bad Request"),
+    (401, UNAUTHORIZED, "Unauthorized"),
+    (402, PAYMENT_REQUIRED, "Payment Required"),
+    (403, FORBIDDEN, "Forbidden"),
+    (404, NOT_FOUND, "Not Found"),
+    (405, METHOD_NOT_ALLOWED, "Method Not Allowed"),
+    (406, NOT_ACCEPTABLE, "Not Acceptable"),
+    (407, PROXY_AUTHENTICATION_REQUIRED, "Proxy Authentication Required"),
+    (408, REQUEST_TIMEOUT, "Request Timeout"),
+    (409, CONFLICT, "Conflict"),
+    (410, GONE, "Gone"),
+    (411, LENGTH_REQUIRED, "Length Required"),
+    (412, PRECONDITION_FAILED, "Precondition Failed"),
+    (413, CONTENT_TOO_LARGE, "Content Too Large"),
+    (414, URI_TOO_LONG, "URI Too Long"),
+    (415, UNSUPPORTED_MEDIA_TYPE, "Unsupported Media Type"),
+    (416, RANGE_NOT_SATISFIABLE, "Range Not Satisfiable"),
+    (417, EXPECTATION_FAILED, "Expectation Failed"),
+    (418, IM_A_TEAPOT, "I'm a teapot"),
+    (421, MISDIRECTED_REQUEST, "Misdirected Request"),
+    (422, UNPROCESSABLE_CONTENT, "Unprocessable Content"),
+    (423, LOCKED, "Locked"),
+    (424, FAILED_DEPENDENCY, "Failed Dependency"),
+    (425, TOO_EARLY, "Too Early"),
+    (426, UPGRADE_REQUIRED, "Upgrade Required"),
+    (428, PRECONDITION_REQUIRED, "Precondition Required"),
+    (429, TOO_MANY_REQUESTS, "Too Many Requests"),
+    (431, REQUEST_HEADER_FIELDS_TOO_LARGE, "Request Header Fields Too Large"),
+    (451, UNAVAILABLE_FOR_LEGAL_REASONS, "Unavailable For Legal Reasons"),
+    (500, INTERNAL_SERVER_ERROR, "Internal Server Error"),
+    (501, NOT_IMPLEMENTED, "Not Implemented"),
+    (502, BAD_GATEWAY, "Bad Gateway"),
+    (503, SERVICE_UNAVAILABLE, "Service Unavailable"),
+    (504, GATEWAY_TIMEOUT, "Gateway Timeout"),
+    (505, HTTP_VERSION_NOT_SUPPORTED, "HTTP Version Not Supported"),
+    (506, VARIANT_ALSO_NEGOTIATES, "Variant Also Negotiates"),
+    (507, INSUFFICIENT_STORAGE, "Insufficient Storage"),
+    (508, LOOP_DETECTED, "Loop Detected"),
+    (510, NOT_EXTENDED, "Not Extended"),
+    (511, NETWORK_AUTHENTICATION_REQUIRED, "Network Authentication Required"),
+}
+
+impl StatusCode {
+    /// Fails with [`HttpError::InternalServerError`] unless `code` has three digits
+    pub fn from_u16(code: u16) -> Result<StatusCode, HttpError> {
+        if !(100..=999).contains(&code) {
+            return Err(HttpError::InternalServerError {
+                message: format!("Invalid status code `{code}`"),
+            });
+        }
+
+        Ok(StatusCode(code))
+    }
+
+    pub fn as_u16(&self) -> u16 {
+        self.0
+    }
+
+    /// `1xx`
+    pub fn is_informational(&self) -> bool {
+        (100..200).contains(&self.0)
+    }
+
+    /// `2xx`
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.0)
+    }
+
+    /// `3xx`
+    pub fn is_redirection(&self) -> bool {
+        (300..400).contains(&self.0)
+    }
+
+    /// `4xx`
+    pub fn is_client_error(&self) -> bool {
+        (400..500).contains(&self.0)
+    }
+
+    /// `5xx`
+    pub fn is_server_error(&self) -> bool {
+        (500..600).contains(&self.0)
+    }
+}
+
+impl Default for StatusCode {
+    fn default() -> Self {
+        StatusCode::OK
+    }
+}
+
+impl TryFrom<u16> for StatusCode {
+    type Error = HttpError;
+
+    fn try_from(code: u16) -> Result<Self, Self::Error> {
+        StatusCode::from_u16(code)
+    }
+}
+
+impl From<StatusCode> for u16 {
+    fn from(status: StatusCode) -> Self {
+        status.0
+    }
+}
+
+/// The status line form, e.g. `404 Not Found`
+impl Display for StatusCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", self.0, self.reason_phrase().unwrap_or_default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn has_reason_phrases() {
+        assert_eq!(StatusCode::OK.reason_phrase(), Some("OK"));
+        assert_eq!(
+            StatusCode::IM_A_TEAPOT.reason_phrase(),
+            Some("I'm a teapot")
+        );
+        assert_eq!(
+            StatusCode::HTTP_VERSION_NOT_SUPPORTED.reason_phrase(),
+            Some("HTTP Version Not Supported")
+        );
+        assert_eq!(StatusCode::from_u16(599).unwrap().reason_phrase(), None);
+
+        assert_eq!(StatusCode::NOT_FOUND.to_string(), "404 Not Found");
+        // The space before an empty reason phrase is still required by the status line
+        assert_eq!(StatusCode::from_u16(599).unwrap().to_string(), "599 ");
+    }
+
+    #[test]
+    fn accepts_any_three_digit_code() {
+        for code in [100, 299, 599, 999] {
+            assert_eq!(StatusCode::from_u16(code).unwrap().as_u16(), code);
+        }
+
+        assert_eq!(StatusCode::from_u16(404).unwrap(), StatusCode::NOT_FOUND);
+        assert_eq!(u16::from(StatusCode::CREATED), 201);
+
+        for code in [0, 99, 1000, u16::MAX] {
+            assert!(matches!(
+                StatusCode::try_from(code),
+                Err(HttpError::InternalServerError { .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn classifies_codes_at_the_class_boundaries() {
+        let class = |code| {
+            let status = StatusCode::from_u16(code).unwrap();
+            [
+                status.is_informational(),
+                status.is_success(),
+                status.is_redirection(),
+                status.is_client_error(),
+                status.is_server_error(),
+            ]
+            .iter()
+            .position(|&is_class| is_class)
+        };
+
+        assert_eq!(class(100), Some(0));
+        assert_eq!(class(199), Some(0));
+        assert_eq!(class(200), Some(1));
+        assert_eq!(class(299), Some(1));
+        assert_eq!(class(300), Some(2));
+        assert_eq!(class(399), Some(2));
+        assert_eq!(class(400), Some(3));
+        assert_eq!(class(499), Some(3));
+        assert_eq!(class(500), Some(4));
+        assert_eq!(class(599), Some(4));
+        assert_eq!(class(600), None);
+        assert_eq!(class(999), None);
+    }
+}
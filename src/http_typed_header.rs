@@ -0,0 +1,979 @@
+use crate::{format_http_date, http_header::is_token, parse_http_date, HttpError};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chrono::{DateTime, Utc};
+use std::fmt::Write;
+
+/// A header whose value has a structure, read with
+/// [`HttpRequest::get_typed_header`](crate::HttpRequest::get_typed_header) and written with
+/// [`HttpResponse::set_typed_header`](crate::HttpResponse::set_typed_header)
+pub trait TypedHeader: Sized {
+    const NAME: &'static str;
+
+    /// Parses the header value, failing with [`HttpError::BadRequest`] if it is malformed
+    fn decode(value: &str) -> Result<Self, HttpError>;
+
+    fn encode(&self) -> String;
+}
+
+fn invalid<T: TypedHeader>() -> HttpError {
+    HttpError::BadRequest {
+        message: format!("Invalid `{}` header", T::NAME),
+    }
+}
+
+/// Splits `value` on `separator`, ignoring separators inside quoted strings
+pub(crate) fn split_unquoted(value: &str, separator: char) -> Vec<&str> {
+    let mut elements = vec![];
+    let mut start = 0;
+    let mut quoted = false;
+    let mut escaped = false;
+
+    for (index, char) in value.char_indices() {
+        match char {
+            _ if escaped => escaped = false,
+            '\\' if quoted => escaped = true,
+            '"' => quoted = !quoted,
+            char if char == separator && !quoted => {
+                elements.push(value[start..index].trim());
+                start = index + 1;
+            }
+            _ => {}
+        }
+    }
+
+    elements.push(value[start..].trim());
+    elements
+}
+
+/// Splits a comma separated list, ignoring commas inside quoted strings and empty elements
+fn split_list(value: &str) -> Vec<&str> {
+    let mut elements = split_unquoted(value, ',');
+    elements.retain(|element| !element.is_empty());
+    elements
+}
+
+/// Parses a token or a quoted string, removing the quotes and escapes of the latter
+fn parse_token_or_quoted(value: &str) -> Option<String> {
+    let Some(quoted) = value.strip_prefix('"') else {
+        return is_token(value).then(|| value.to_string());
+    };
+
+    let mut unquoted = String::new();
+    let mut chars = quoted.chars();
+
+    loop {
+        match chars.next()? {
+            '"' => return chars.next().is_none().then_some(unquoted),
+            '\\' => unquoted.push(chars.next()?),
+            char => unquoted.push(char),
+        }
+    }
+}
+
+fn write_token_or_quoted(output: &mut String, value: &str) {
+    if is_token(value) {
+        output.push_str(value);
+    } else {
+        output.push('"');
+
+        for char in value.chars() {
+            if char == '"' || char == '\\' {
+                output.push('\\');
+            }
+
+            output.push(char);
+        }
+
+        output.push('"');
+    }
+}
+
+/// Parses `;` separated `name=value` parameters, lowercasing their names
+pub(crate) fn parse_parameters<'a>(
+    parameters: impl Iterator<Item = &'a str>,
+) -> Option<Vec<(String, String)>> {
+    parameters
+        .map(str::trim)
+        .filter(|parameter| !parameter.is_empty())
+        .map(|parameter| {
+            let (name, value) = parameter.split_once('=')?;
+            let name = name.trim();
+
+            if !is_token(name) {
+                return None;
+            }
+
+            Some((
+                name.to_ascii_lowercase(),
+                parse_token_or_quoted(value.trim())?,
+            ))
+        })
+        .collect()
+}
+
+/// Parses a `type/subtype` media type, lowercasing it
+fn parse_media_type(value: &str) -> Option<String> {
+    let (main_type, subtype) = value.trim().split_once('/')?;
+
+    (is_token(main_type) && is_token(subtype)).then(|| value.trim().to_ascii_lowercase())
+}
+
+/// The `Content-Type` header, e.g. `text/html; charset=utf-8`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContentType {
+    media_type: String,
+    parameters: Vec<(String, String)>,
+}
+
+impl ContentType {
+    /// Fails with [`HttpError::BadRequest`] if `media_type` is not of the form `type/subtype`
+    pub fn new(media_type: &str) -> Result<ContentType, HttpError> {
+        Ok(ContentType {
+            media_type: parse_media_type(media_type).ok_or_else(invalid::<ContentType>)?,
+            parameters: vec![],
+        })
+    }
+
+    pub fn with_parameter(mut self, name: &str, value: &str) -> ContentType {
+        self.parameters
+            .push((name.to_ascii_lowercase(), value.to_string()));
+        self
+    }
+
+    /// The lowercased media type, without its parameters
+    pub fn media_type(&self) -> &str {
+        &self.media_type
+    }
+
+    pub fn parameter(&self, name: &str) -> Option<&str> {
+        self.parameters
+            .iter()
+            .find(|(existing, _)| existing.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn charset(&self) -> Option<&str> {
+        self.parameter("charset")
+    }
+}
+
+impl TypedHeader for ContentType {
+    const NAME: &'static str = "Content-Type";
+
+    fn decode(value: &str) -> Result<ContentType, HttpError> {
+        let mut parts = split_unquoted(value, ';').into_iter();
+
+        Ok(ContentType {
+            media_type: parse_media_type(parts.next().unwrap_or_default())
+                .ok_or_else(invalid::<ContentType>)?,
+            parameters: parse_parameters(parts).ok_or_else(invalid::<ContentType>)?,
+        })
+    }
+
+    fn encode(&self) -> String {
+        let mut value = self.media_type.clone();
+
+        for (name, parameter) in &self.parameters {
+            let _ = write!(value, "; {name}=");
+            write_token_or_quoted(&mut value, parameter);
+        }
+
+        value
+    }
+}
+
+/// A single media range of an [`Accept`] header, e.g. `text/*;q=0.8`
+#[derive(Debug, Clone, PartialEq)]
+pub struct MediaRange {
+    media_type: String,
+    parameters: Vec<(String, String)>,
+    quality: f32,
+}
+
+impl MediaRange {
+    pub fn media_type(&self) -> &str {
+        &self.media_type
+    }
+
+    pub fn parameter(&self, name: &str) -> Option<&str> {
+        self.parameters
+            .iter()
+            .find(|(existing, _)| existing.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// The `q` weight, between 0 and 1
+    pub fn quality(&self) -> f32 {
+        self.quality
+    }
+
+    /// Whether the range includes `media_type`, e.g. `text/*` includes `text/html`
+    pub fn matches(&self, media_type: &str) -> bool {
+        let Some((main_type, subtype)) = media_type.split_once('/') else {
+            return false;
+        };
+
+        match self.media_type.split_once('/') {
+            Some(("*", "*")) => true,
+            Some((range_type, "*")) => range_type.eq_ignore_ascii_case(main_type),
+            Some((range_type, range_subtype)) => {
+                range_type.eq_ignore_ascii_case(main_type)
+                    && range_subtype.eq_ignore_ascii_case(subtype)
+            }
+            None => false,
+        }
+    }
+
+    /// How specific the range is, so `text/html` wins over `text/*`, which wins over `*/*`
+    fn specificity(&self) -> usize {
+        match self.media_type.split_once('/') {
+            Some(("*", _)) => 0,
+            Some((_, "*")) => 1,
+            _ => 2 + self.parameters.len(),
+        }
+    }
+}
+
+/// The `Accept` header, with its media ranges ordered from most to least preferred
+#[derive(Debug, Clone, PartialEq)]
+pub struct Accept {
+    ranges: Vec<MediaRange>,
+}
+
+impl Accept {
+    pub fn ranges(&self) -> &[MediaRange] {
+        &self.ranges
+    }
+
+    /// The weight given to `media_type` by its most specific matching range, or 0 if none
+    /// matches
+    pub fn quality(&self, media_type: &str) -> f32 {
+        self.ranges
+            .iter()
+            .filter(|range| range.matches(media_type))
+            .max_by_key(|range| range.specificity())
+            .map_or(0.0, |range| range.quality)
+    }
+
+    /// The one of `available` the client prefers, ignoring those it does not accept.
+    /// Ties go to the first one in `available`.
+    pub fn preferred<'a>(&self, available: &[&'a str]) -> Option<&'a str> {
+        available
+            .iter()
+            .map(|media_type| (*media_type, self.quality(media_type)))
+            .filter(|(_, quality)| *quality > 0.0)
+            .fold(
+                None,
+                |best: Option<(&str, f32)>, (media_type, quality)| match best {
+                    Some((_, best_quality)) if best_quality >= quality => best,
+                    _ => Some((media_type, quality)),
+                },
+            )
+            .map(|(media_type, _)| media_type)
+    }
+}
+
+impl TypedHeader for Accept {
+    const NAME: &'static str = "Accept";
+
+    fn decode(value: &str) -> Result<Accept, HttpError> {
+        let mut ranges = split_list(value)
+            .into_iter()
+            .map(|element| {
+                let mut parts = split_unquoted(element, ';').into_iter();
+                let media_type = parse_media_type(parts.next()?)?;
+                let mut parameters = parse_parameters(parts)?;
+
+                let quality = match parameters.iter().position(|(name, _)| name == "q") {
+                    // Parameters after the weight are extensions of the weight, not the media type
+                    Some(index) => {
+                        let quality = parameters[index].1.parse::<f32>().ok()?;
+                        parameters.truncate(index);
+                        quality
+                    }
+                    None => 1.0,
+                };
+
+                (0.0..=1.0).contains(&quality).then_some(MediaRange {
+                    media_type,
+                    parameters,
+                    quality,
+                })
+            })
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(invalid::<Accept>)?;
+
+        // Stable, so ranges of the same weight keep the order the client sent them in
+        ranges.sort_by(|a, b| b.quality.total_cmp(&a.quality));
+
+        Ok(Accept { ranges })
+    }
+
+    fn encode(&self) -> String {
+        self.ranges
+            .iter()
+            .map(|range| {
+                let mut value = range.media_type.clone();
+
+                for (name, parameter) in &range.parameters {
+                    let _ = write!(value, ";{name}=");
+                    write_token_or_quoted(&mut value, parameter);
+                }
+
+                if range.quality < 1.0 {
+                    let _ = write!(value, ";q={}", range.quality);
+                }
+
+                value
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+/// The `Authorization` header, made of a scheme and its credentials
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Authorization {
+    scheme: String,
+    credentials: String,
+}
+
+impl Authorization {
+    pub fn basic(username: &str, password: &str) -> Authorization {
+        Authorization {
+            scheme: "Basic".to_string(),
+            credentials: STANDARD.encode(format!("{username}:{password}")),
+        }
+    }
+
+    pub fn bearer(token: &str) -> Authorization {
+        Authorization {
+            scheme: "Bearer".to_string(),
+            credentials: token.to_string(),
+        }
+    }
+
+    pub fn scheme(&self) -> &str {
+        &self.scheme
+    }
+
+    pub fn credentials(&self) -> &str {
+        &self.credentials
+    }
+
+    /// The username and password of the `Basic` scheme, or `None` for any other scheme or
+    /// credentials that do not decode
+    pub fn as_basic(&self) -> Option<(String, String)> {
+        if !self.scheme.eq_ignore_ascii_case("Basic") {
+            return None;
+        }
+
+        let decoded = String::from_utf8(STANDARD.decode(&self.credentials).ok()?).ok()?;
+        let (username, password) = decoded.split_once(':')?;
+
+        Some((username.to_string(), password.to_string()))
+    }
+
+    /// The token of the `Bearer` scheme
+    pub fn as_bearer(&self) -> Option<&str> {
+        self.scheme
+            .eq_ignore_ascii_case("Bearer")
+            .then_some(self.credentials.as_str())
+    }
+}
+
+impl TypedHeader for Authorization {
+    const NAME: &'static str = "Authorization";
+
+    fn decode(value: &str) -> Result<Authorization, HttpError> {
+        let (scheme, credentials) = value.trim().split_once(' ').unwrap_or((value.trim(), ""));
+
+        if !is_token(scheme) {
+            return Err(invalid::<Authorization>());
+        }
+
+        Ok(Authorization {
+            scheme: scheme.to_string(),
+            credentials: credentials.trim().to_string(),
+        })
+    }
+
+    fn encode(&self) -> String {
+        if self.credentials.is_empty() {
+            self.scheme.clone()
+        } else {
+            format!("{} {}", self.scheme, self.credentials)
+        }
+    }
+}
+
+/// The `Host` header, e.g. `example.com:8080` or `[::1]:8080`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Host {
+    hostname: String,
+    port: Option<u16>,
+}
+
+impl Host {
+    pub fn new(hostname: &str, port: Option<u16>) -> Host {
+        Host {
+            hostname: hostname.to_string(),
+            port,
+        }
+    }
+
+    /// The host name or IP address, with IPv6 addresses in brackets
+    pub fn hostname(&self) -> &str {
+        &self.hostname
+    }
+
+    pub fn port(&self) -> Option<u16> {
+        self.port
+    }
+}
+
+impl TypedHeader for Host {
+    const NAME: &'static str = "Host";
+
+    fn decode(value: &str) -> Result<Host, HttpError> {
+        let value = value.trim();
+
+        // The colons of an IPv6 address are inside the brackets
+        let port_separator = match value.rfind(']') {
+            Some(end) => value[end..].find(':').map(|index| end + index),
+            None => value.rfind(':'),
+        };
+
+        let (hostname, port) = match port_separator {
+            Some(index) => (
+                &value[..index],
+                Some(value[index + 1..].parse().map_err(|_| invalid::<Host>())?),
+            ),
+            None => (value, None),
+        };
+
+        let is_valid =
+            |char: char| char.is_ascii_alphanumeric() || "-._~!$&'()*+,;=%[]:".contains(char);
+
+        if !hostname.chars().all(is_valid) {
+            return Err(invalid::<Host>());
+        }
+
+        Ok(Host::new(hostname, port))
+    }
+
+    fn encode(&self) -> String {
+        match self.port {
+            Some(port) => format!("{}:{port}", self.hostname),
+            None => self.hostname.clone(),
+        }
+    }
+}
+
+/// An entity tag as sent in the `ETag` header, e.g. `"abc"` or `W/"abc"`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ETag {
+    tag: String,
+    weak: bool,
+}
+
+impl ETag {
+    /// Fails with [`HttpError::BadRequest`] if `tag` contains a `"` or characters not allowed
+    /// in an entity tag
+    pub fn strong(tag: &str) -> Result<ETag, HttpError> {
+        ETag::new(tag, false)
+    }
+
+    pub fn weak(tag: &str) -> Result<ETag, HttpError> {
+        ETag::new(tag, true)
+    }
+
+    fn new(tag: &str, weak: bool) -> Result<ETag, HttpError> {
+        // `etagc` from RFC 9110, section 8.8.3
+        if !tag
+            .bytes()
+            .all(|byte| byte == 0x21 || (0x23..=0x7e).contains(&byte) || byte >= 0x80)
+        {
+            return Err(invalid::<ETag>());
+        }
+
+        Ok(ETag {
+            tag: tag.to_string(),
+            weak,
+        })
+    }
+
+    pub fn tag(&self) -> &str {
+        &self.tag
+    }
+
+    pub fn is_weak(&self) -> bool {
+        self.weak
+    }
+
+    /// Strong comparison, where both tags must be strong and equal
+    pub fn strong_eq(&self, other: &ETag) -> bool {
+        !self.weak && !other.weak && self.tag == other.tag
+    }
+
+    /// Weak comparison, where only the tags must be equal
+    pub fn weak_eq(&self, other: &ETag) -> bool {
+        self.tag == other.tag
+    }
+}
+
+impl TypedHeader for ETag {
+    const NAME: &'static str = "ETag";
+
+    fn decode(value: &str) -> Result<ETag, HttpError> {
+        let value = value.trim();
+        let (weak, quoted) = match value.strip_prefix("W/") {
+            Some(quoted) => (true, quoted),
+            None => (false, value),
+        };
+
+        let tag = quoted
+            .strip_prefix('"')
+            .and_then(|tag| tag.strip_suffix('"'))
+            .ok_or_else(invalid::<ETag>)?;
+
+        ETag::new(tag, weak)
+    }
+
+    fn encode(&self) -> String {
+        let prefix = if self.weak { "W/" } else { "" };
+        format!("{prefix}\"{}\"", self.tag)
+    }
+}
+
+/// The `If-None-Match` header, either `*` or a list of entity tags
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IfNoneMatch {
+    Any,
+    Tags(Vec<ETag>),
+}
+
+impl IfNoneMatch {
+    /// Whether a resource with `etag` matches, using weak comparison as RFC 9110 requires
+    /// for this header
+    pub fn matches(&self, etag: &ETag) -> bool {
+        match self {
+            IfNoneMatch::Any => true,
+            IfNoneMatch::Tags(tags) => tags.iter().any(|tag| tag.weak_eq(etag)),
+        }
+    }
+}
+
+impl TypedHeader for IfNoneMatch {
+    const NAME: &'static str = "If-None-Match";
+
+    fn decode(value: &str) -> Result<IfNoneMatch, HttpError> {
+        if value.trim() == "*" {
+            return Ok(IfNoneMatch::Any);
+        }
+
+        let tags = split_list(value)
+            .into_iter()
+            .map(ETag::decode)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| invalid::<IfNoneMatch>())?;
+
+        if tags.is_empty() {
+            return Err(invalid::<IfNoneMatch>());
+        }
+
+        Ok(IfNoneMatch::Tags(tags))
+    }
+
+    fn encode(&self) -> String {
+        match self {
+            IfNoneMatch::Any => "*".to_string(),
+            IfNoneMatch::Tags(tags) => tags.iter().map(ETag::encode).collect::<Vec<_>>().join(", "),
+        }
+    }
+}
+
+/// The `If-Modified-Since` header
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IfModifiedSince(pub DateTime<Utc>);
+
+impl IfModifiedSince {
+    /// Whether a resource last modified at `last_modified` has changed since this date.
+    /// HTTP dates have a precision of one second, so fractions of seconds are ignored.
+    pub fn is_modified(&self, last_modified: DateTime<Utc>) -> bool {
+        last_modified.timestamp() > self.0.timestamp()
+    }
+}
+
+impl TypedHeader for IfModifiedSince {
+    const NAME: &'static str = "If-Modified-Since";
+
+    fn decode(value: &str) -> Result<IfModifiedSince, HttpError> {
+        parse_http_date(value)
+            .map(IfModifiedSince)
+            .ok_or_else(invalid::<IfModifiedSince>)
+    }
+
+    fn encode(&self) -> String {
+        format_http_date(self.0)
+    }
+}
+
+/// A single range of a [`Range`] header
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ByteRange {
+    /// `first-last`, both inclusive
+    FromTo(u64, u64),
+    /// `first-`, up to the end
+    From(u64),
+    /// `-length`, the last `length` bytes
+    Last(u64),
+}
+
+impl ByteRange {
+    /// The inclusive start and end of the range within a representation of `length` bytes,
+    /// or `None` if the range is not satisfiable
+    pub fn bounds(&self, length: u64) -> Option<(u64, u64)> {
+        let (start, end) = match *self {
+            ByteRange::FromTo(start, end) => (start, end.min(length.checked_sub(1)?)),
+            ByteRange::From(start) => (start, length.checked_sub(1)?),
+            ByteRange::Last(0) => return None,
+            ByteRange::Last(suffix) => (length.saturating_sub(suffix), length.checked_sub(1)?),
+        };
+
+        (start <= end).then_some((start, end))
+    }
+}
+
+/// The `Range` header, e.g. `bytes=0-499, -100`. Only the `bytes` unit is supported.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Range {
+    ranges: Vec<ByteRange>,
+}
+
+impl Range {
+    pub fn new(ranges: Vec<ByteRange>) -> Range {
+        Range { ranges }
+    }
+
+    pub fn ranges(&self) -> &[ByteRange] {
+        &self.ranges
+    }
+}
+
+impl TypedHeader for Range {
+    const NAME: &'static str = "Range";
+
+    fn decode(value: &str) -> Result<Range, HttpError> {
+        let (unit, ranges) = value.trim().split_once('=').ok_or_else(invalid::<Range>)?;
+
+        if !unit.trim().eq_ignore_ascii_case("bytes") {
+            return Err(invalid::<Range>());
+        }
+
+        let ranges = split_list(ranges)
+            .into_iter()
+            .map(|range| {
+                let (start, end) = range.split_once('-')?;
+                let parse = |value: &str| value.trim().parse::<u64>().ok();
+
+                match (start.trim().is_empty(), end.trim().is_empty()) {
+                    (true, false) => Some(ByteRange::Last(parse(end)?)),
+                    (false, true) => Some(ByteRange::From(parse(start)?)),
+                    (false, false) => {
+                        let (start, end) = (parse(start)?, parse(end)?);
+                        (start <= end).then_some(ByteRange::FromTo(start, end))
+                    }
+                    (true, true) => None,
+                }
+            })
+            .collect::<Option<Vec<_>>>()
+            .filter(|ranges| !ranges.is_empty())
+            .ok_or_else(invalid::<Range>)?;
+
+        Ok(Range { ranges })
+    }
+
+    fn encode(&self) -> String {
+        let ranges: Vec<_> = self
+            .ranges
+            .iter()
+            .map(|range| match range {
+                ByteRange::FromTo(start, end) => format!("{start}-{end}"),
+                ByteRange::From(start) => format!("{start}-"),
+                ByteRange::Last(length) => format!("-{length}"),
+            })
+            .collect();
+
+        format!("bytes={}", ranges.join(", "))
+    }
+}
+
+/// The `Cache-Control` header, as a list of directives with optional arguments
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CacheControl {
+    directives: Vec<(String, Option<String>)>,
+}
+
+impl CacheControl {
+    pub fn new() -> CacheControl {
+        CacheControl::default()
+    }
+
+    /// Adds a directive, e.g. `no-store` or `max-age` with `Some("60")`
+    pub fn with_directive(mut self, name: &str, argument: Option<&str>) -> CacheControl {
+        self.directives
+            .push((name.to_ascii_lowercase(), argument.map(str::to_string)));
+        self
+    }
+
+    pub fn with_max_age(self, seconds: u64) -> CacheControl {
+        self.with_directive("max-age", Some(&seconds.to_string()))
+    }
+
+    pub fn directives(&self) -> &[(String, Option<String>)] {
+        &self.directives
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.directives
+            .iter()
+            .any(|(existing, _)| existing.eq_ignore_ascii_case(name))
+    }
+
+    /// The argument of the directive `name`
+    pub fn argument(&self, name: &str) -> Option<&str> {
+        self.directives
+            .iter()
+            .find(|(existing, _)| existing.eq_ignore_ascii_case(name))
+            .and_then(|(_, argument)| argument.as_deref())
+    }
+
+    pub fn max_age(&self) -> Option<u64> {
+        self.argument("max-age")?.parse().ok()
+    }
+
+    pub fn no_cache(&self) -> bool {
+        self.contains("no-cache")
+    }
+
+    pub fn no_store(&self) -> bool {
+        self.contains("no-store")
+    }
+}
+
+impl TypedHeader for CacheControl {
+    const NAME: &'static str = "Cache-Control";
+
+    fn decode(value: &str) -> Result<CacheControl, HttpError> {
+        let directives = split_list(value)
+            .into_iter()
+            .map(|directive| match directive.split_once('=') {
+                Some((name, argument)) => Some((
+                    name.trim().to_ascii_lowercase(),
+                    Some(parse_token_or_quoted(argument.trim())?),
+                )),
+                None => Some((directive.to_ascii_lowercase(), None)),
+            })
+            .collect::<Option<Vec<_>>>()
+            .filter(|directives| directives.iter().all(|(name, _)| is_token(name)))
+            .ok_or_else(invalid::<CacheControl>)?;
+
+        Ok(CacheControl { directives })
+    }
+
+    fn encode(&self) -> String {
+        self.directives
+            .iter()
+            .map(|(name, argument)| {
+                let mut value = name.clone();
+
+                if let Some(argument) = argument {
+                    value.push('=');
+                    write_token_or_quoted(&mut value, argument);
+                }
+
+                value
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+/// The `Location` header, a URI reference that is not checked beyond being a valid header value
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Location(pub String);
+
+impl TypedHeader for Location {
+    const NAME: &'static str = "Location";
+
+    fn decode(value: &str) -> Result<Location, HttpError> {
+        let value = value.trim();
+
+        if value.is_empty() || value.contains(char::is_whitespace) {
+            return Err(invalid::<Location>());
+        }
+
+        Ok(Location(value.to_string()))
+    }
+
+    fn encode(&self) -> String {
+        self.0.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn media_types(accept: &Accept) -> Vec<&str> {
+        accept.ranges().iter().map(MediaRange::media_type).collect()
+    }
+
+    #[test]
+    fn orders_accept_ranges_by_quality() {
+        let accept =
+            Accept::decode("text/*;q=0.5, application/json, */*;q=0.1, text/html;level=1").unwrap();
+
+        assert_eq!(
+            media_types(&accept),
+            vec!["application/json", "text/html", "text/*", "*/*"]
+        );
+        assert_eq!(accept.ranges()[1].parameter("level"), Some("1"));
+        assert_eq!(accept.ranges()[2].quality(), 0.5);
+    }
+
+    #[test]
+    fn weighs_media_types_by_their_most_specific_range() {
+        let accept =
+            Accept::decode("text/*;q=0.5, text/html;q=0.9, */*;q=0.1, image/png;q=0").unwrap();
+
+        assert_eq!(accept.quality("text/html"), 0.9);
+        assert_eq!(accept.quality("text/plain"), 0.5);
+        assert_eq!(accept.quality("application/json"), 0.1);
+        assert_eq!(accept.quality("image/png"), 0.0);
+
+        assert_eq!(
+            accept.preferred(&["application/json", "text/plain", "text/html"]),
+            Some("text/html")
+        );
+        assert_eq!(accept.preferred(&["image/png"]), None);
+
+        // Ties go to the first available type
+        let accept = Accept::decode("text/plain, text/html").unwrap();
+        assert_eq!(
+            accept.preferred(&["text/html", "text/plain"]),
+            Some("text/html")
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_accept_headers() {
+        assert!(Accept::decode("text/html;q=1.5").is_err());
+        assert!(Accept::decode("text/html;q=high").is_err());
+        assert!(Accept::decode("text").is_err());
+    }
+
+    #[test]
+    fn resolves_range_bounds() {
+        assert_eq!(ByteRange::FromTo(0, 499).bounds(1000), Some((0, 499)));
+        assert_eq!(ByteRange::FromTo(900, 1999).bounds(1000), Some((900, 999)));
+        assert_eq!(ByteRange::FromTo(1000, 1999).bounds(1000), None);
+        assert_eq!(ByteRange::From(999).bounds(1000), Some((999, 999)));
+        assert_eq!(ByteRange::From(1000).bounds(1000), None);
+        assert_eq!(ByteRange::Last(100).bounds(1000), Some((900, 999)));
+        assert_eq!(ByteRange::Last(5000).bounds(1000), Some((0, 999)));
+        assert_eq!(ByteRange::Last(0).bounds(1000), None);
+        assert_eq!(ByteRange::From(0).bounds(0), None);
+        assert_eq!(ByteRange::Last(10).bounds(0), None);
+    }
+
+    #[test]
+    fn parses_range_headers() {
+        let range = Range::decode("bytes=0-499, 500- , -100").unwrap();
+        assert_eq!(
+            range.ranges(),
+            &[
+                ByteRange::FromTo(0, 499),
+                ByteRange::From(500),
+                ByteRange::Last(100)
+            ]
+        );
+        assert_eq!(range.encode(), "bytes=0-499, 500-, -100");
+
+        assert!(Range::decode("items=0-1").is_err());
+        assert!(Range::decode("bytes=5-1").is_err());
+        assert!(Range::decode("bytes=-").is_err());
+        assert!(Range::decode("bytes=").is_err());
+        assert!(Range::decode("bytes=a-b").is_err());
+    }
+
+    #[test]
+    fn parses_host_headers() {
+        let host = Host::decode("example.com:8080").unwrap();
+        assert_eq!((host.hostname(), host.port()), ("example.com", Some(8080)));
+
+        let host = Host::decode("[::1]:3000").unwrap();
+        assert_eq!((host.hostname(), host.port()), ("[::1]", Some(3000)));
+
+        let host = Host::decode("[::1]").unwrap();
+        assert_eq!((host.hostname(), host.port()), ("[::1]", None));
+
+        assert!(Host::decode("example.com:http").is_err());
+        assert!(Host::decode("exa mple.com").is_err());
+    }
+
+    #[test]
+    fn compares_entity_tags() {
+        let strong = ETag::decode("\"v1\"").unwrap();
+        let weak = ETag::decode("W/\"v1\"").unwrap();
+
+        assert!(weak.is_weak());
+        assert!(strong.strong_eq(&strong));
+        assert!(!strong.strong_eq(&weak));
+        assert!(strong.weak_eq(&weak));
+        assert_eq!(weak.encode(), "W/\"v1\"");
+        assert!(ETag::decode("v1").is_err());
+
+        let if_none_match = IfNoneMatch::decode("\"v0\", W/\"v1\"").unwrap();
+        assert!(if_none_match.matches(&strong));
+        assert!(!if_none_match.matches(&ETag::strong("v2").unwrap()));
+        assert!(IfNoneMatch::decode("*").unwrap().matches(&strong));
+    }
+
+    #[test]
+    fn decodes_authorization_credentials() {
+        let basic = Authorization::decode(&Authorization::basic("ada", "p:ss").encode()).unwrap();
+        assert_eq!(
+            basic.as_basic(),
+            Some(("ada".to_string(), "p:ss".to_string()))
+        );
+        assert_eq!(basic.as_bearer(), None);
+
+        let bearer = Authorization::decode("bearer abc.def").unwrap();
+        assert_eq!(bearer.as_bearer(), Some("abc.def"));
+        assert_eq!(bearer.as_basic(), None);
+
+        assert_eq!(Authorization::decode("Basic !!!").unwrap().as_basic(), None);
+    }
+
+    #[test]
+    fn compares_modification_dates() {
+        let since = IfModifiedSince::decode("Sun, 06 Nov 1994 08:49:37 GMT").unwrap();
+
+        assert!(!since.is_modified(since.0));
+        assert!(!since.is_modified(since.0 + chrono::Duration::milliseconds(500)));
+        assert!(since.is_modified(since.0 + chrono::Duration::seconds(1)));
+        assert!(IfModifiedSince::decode("yesterday").is_err());
+    }
+
+    #[test]
+    fn parses_cache_control_directives() {
+        let cache_control = CacheControl::decode("no-cache, max-age=60, private=\"x, y\"").unwrap();
+
+        assert!(cache_control.no_cache());
+        assert!(!cache_control.no_store());
+        assert_eq!(cache_control.max_age(), Some(60));
+        assert_eq!(cache_control.argument("private"), Some("x, y"));
+    }
+}
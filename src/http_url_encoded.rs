@@ -0,0 +1,66 @@
+use crate::HttpError;
+use serde::de::DeserializeOwned;
+
+/// Name/value pairs decoded from a query string or an `application/x-www-form-urlencoded`
+/// body, keeping their order and any repeated names
+#[derive(Debug, Clone, Default)]
+pub struct UrlEncodedMap {
+    pairs: Vec<(String, String)>,
+    raw: String,
+}
+
+impl UrlEncodedMap {
+    pub fn parse(input: &str) -> UrlEncodedMap {
+        UrlEncodedMap {
+            pairs: form_urlencoded::parse(input.as_bytes())
+                .into_owned()
+                .collect(),
+            raw: input.to_string(),
+        }
+    }
+
+    /// The first value for `name`
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.iter()
+            .find(|(key, _)| *key == name)
+            .map(|(_, value)| value)
+    }
+
+    /// Every value for `name`, in order
+    pub fn get_all(&self, name: &str) -> Vec<&str> {
+        self.iter()
+            .filter(|(key, _)| *key == name)
+            .map(|(_, value)| value)
+            .collect()
+    }
+
+    pub fn contains_key(&self, name: &str) -> bool {
+        self.get(name).is_some()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.pairs
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+    }
+
+    /// The encoded form the pairs were parsed from
+    pub fn as_str(&self) -> &str {
+        &self.raw
+    }
+
+    pub fn len(&self) -> usize {
+        self.pairs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pairs.is_empty()
+    }
+
+    /// Deserializes the pairs into `T`, failing with [`HttpError::BadRequest`]
+    pub fn deserialize<T: DeserializeOwned>(&self) -> Result<T, HttpError> {
+        serde_urlencoded::from_str(&self.raw).map_err(|error| HttpError::BadRequest {
+            message: format!("Invalid url-encoded data: {error}"),
+        })
+    }
+}
@@ -1,20 +1,48 @@
 mod config;
 mod http_body;
+mod http_body_reader;
+mod http_chunked;
 mod http_cookie;
+mod http_cookie_jar;
+mod http_date;
 mod http_error;
+mod http_extensions;
 mod http_header;
+mod http_header_map;
 mod http_method;
+mod http_middleware;
+mod http_multipart;
 mod http_request;
 mod http_response;
 mod http_router;
 mod http_server;
+mod http_session;
+mod http_status;
+mod http_typed_header;
+mod http_url_encoded;
 pub use config::HyperionConfig;
-pub use http_body::HttpBody;
-pub use http_cookie::HttpCookie;
+pub use http_body::{ByteStream, HttpBody};
+pub use http_body_reader::BodyReader;
+pub use http_cookie::{HttpCookie, HttpCookieBuilder, SameSite};
+pub use http_cookie_jar::{CookieKey, CookieKeys, PrivateCookieJar, SignedCookieJar};
+pub use http_date::{format_http_date, parse_http_date};
 pub use http_error::HttpError;
+pub use http_extensions::HttpExtensions;
 pub use http_header::HttpHeader;
+pub use http_header_map::HttpHeaderMap;
 pub use http_method::HttpMethod;
+pub use http_middleware::{Middleware, Next};
+pub use http_multipart::{Multipart, MultipartLimits, MultipartPart};
 pub use http_request::{HttpRequest, HttpVersion};
-pub use http_response::HttpResponse;
-pub use http_router::ROUTER;
-pub use http_server::HttpServer;
+pub use http_response::{HttpResponse, HttpResponseBuilder, IntoResponse};
+pub use http_router::HttpRouter;
+pub use http_server::{HttpLimits, HttpServer, HttpServerBuilder};
+pub use http_session::{
+    FileSessionStore, MemorySessionStore, Session, SessionMiddleware, SessionRecord, SessionStore,
+};
+pub use http_status::StatusCode;
+pub use http_typed_header::{
+    Accept, Authorization, ByteRange, CacheControl, ContentType, ETag, Host, IfModifiedSince,
+    IfNoneMatch, Location, MediaRange, Range, TypedHeader,
+};
+pub use http_url_encoded::UrlEncodedMap;